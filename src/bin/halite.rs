@@ -0,0 +1,87 @@
+extern crate my_bot;
+
+use my_bot::engine;
+use my_bot::engine::EngineConfig;
+use std::env;
+use std::process::exit;
+use std::str::FromStr;
+
+const USAGE: &str = "Usage: halite [options] <bot command>...
+
+Options:
+    --width <n>         Map width (default 32)
+    --height <n>        Map height (default 32)
+    -s, --seed <n>      Map seed (default: current time)
+    --turn-limit <n>    Override the number of turns
+    --no-timeout        Wait for bots indefinitely
+    --strict            Kick bots for every invalid command
+    -i, --replay-directory <dir>
+                        Save a replay of the game in <dir>
+    -v, --verbose       Report ignored commands on stderr (-vv and -vvv too)
+    -h, --help          Show this message";
+
+fn main() {
+    let mut config = EngineConfig::default();
+    let mut bot_commands: Vec<String> = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => config.width = parse_value(&arg, args.next()),
+            "--height" => config.height = parse_value(&arg, args.next()),
+            "-s" | "--seed" => config.seed = parse_value(&arg, args.next()),
+            "--turn-limit" => config.turn_limit = Some(parse_value(&arg, args.next())),
+            "--no-timeout" => config.timeouts = false,
+            "--strict" => config.strict = true,
+            "-i" | "--replay-directory" => match args.next() {
+                Some(directory) => config.replay_directory = Some(directory),
+                None => usage_error(&format!("{} expects a directory", arg)),
+            },
+            // Accept the official engine's stacked -vvv.
+            "--verbose" => config.verbose = true,
+            _ if arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| c == 'v') => config.verbose = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option {}", arg)),
+            _ => bot_commands.push(arg),
+        }
+    }
+
+    if bot_commands.is_empty() {
+        usage_error("no bots given");
+    }
+
+    println!("Map seed is {}", config.seed);
+
+    match engine::run(&config, &bot_commands) {
+        Ok(results) => {
+            for result in results {
+                print!(
+                    "Player #{}, {}, came in rank #{} with {} halite and was last alive on turn {}",
+                    result.id.0, result.name, result.rank, result.halite, result.last_turn_alive);
+                match result.kicked {
+                    Some(reason) => println!(" (kicked: {}).", reason),
+                    None => println!("."),
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            exit(1);
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(option: &str, value: Option<String>) -> T {
+    match value.as_ref().and_then(|value| value.parse().ok()) {
+        Some(value) => value,
+        None => usage_error(&format!("{} expects a number", option)),
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("Error: {}\n\n{}", message, USAGE);
+    exit(2);
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::process;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+pub enum BotError {
+    Timeout,
+    Closed,
+    Io(io::Error),
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BotError::Timeout => write!(f, "timed out"),
+            BotError::Closed => write!(f, "closed its connection"),
+            BotError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+const POLL_INTERVAL_MILLIS: u64 = 10;
+
+pub struct Bot {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: Receiver<String>,
+}
+
+impl Bot {
+    pub fn spawn(command: &str) -> io::Result<Bot> {
+        let mut child = process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // Lines are read on a separate thread so that receive can time out.
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(Bot { child, stdin: Some(stdin), lines })
+    }
+
+    pub fn send(&mut self, message: &str) -> Result<(), BotError> {
+        let stdin = match &mut self.stdin {
+            Some(stdin) => stdin,
+            None => return Err(BotError::Closed),
+        };
+
+        stdin.write_all(message.as_bytes()).map_err(BotError::Io)?;
+        stdin.flush().map_err(BotError::Io)
+    }
+
+    pub fn receive(&mut self, timeout: Option<Duration>) -> Result<String, BotError> {
+        match timeout {
+            Some(timeout) => self.lines.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => BotError::Timeout,
+                RecvTimeoutError::Disconnected => BotError::Closed,
+            }),
+            None => self.lines.recv().map_err(|_| BotError::Closed),
+        }
+    }
+
+    // Closing stdin tells the bot the game is over. It gets the grace period
+    // to wrap up before it is killed.
+    pub fn shutdown(&mut self, grace: Duration) {
+        self.stdin = None;
+
+        let started = Instant::now();
+        while started.elapsed() < grace {
+            match self.child.try_wait() {
+                Ok(Some(_)) | Err(_) => return,
+                Ok(None) => thread::sleep(Duration::from_millis(POLL_INTERVAL_MILLIS)),
+            }
+        }
+
+        self.kill();
+    }
+
+    pub fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,135 @@
+use hlt::constants::Constants;
+use hlt::game_map::GameMap;
+use hlt::grid::Grid;
+use hlt::map_cell::MapCell;
+use hlt::position::Position;
+use rand::Rng;
+use rand::SeedableRng;
+use rand::XorShiftRng;
+use std::cmp::min;
+
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+    let mut seed_bytes = [0u8; 16];
+    for (i, byte) in seed_bytes.iter_mut().enumerate() {
+        *byte = (seed >> ((i % 8) * 8)) as u8;
+    }
+    SeedableRng::from_seed(seed_bytes)
+}
+
+pub fn tile_layout(num_players: usize) -> Option<(usize, usize)> {
+    match num_players {
+        1 => Some((1, 1)),
+        2 => Some((2, 1)),
+        4 => Some((2, 2)),
+        _ => None,
+    }
+}
+
+// One tile is generated per player and mirrored into the other tiles, so every
+// player starts from an equivalent position. The shipyards are returned in
+// player order. The random source differs from the official engine's, so a
+// seed gives a map from the same distribution rather than the same map.
+pub fn generate(
+    constants: &Constants,
+    seed: u64,
+    width: usize,
+    height: usize,
+    num_players: usize) -> Result<(GameMap, Vec<Position>), String>
+{
+    let (cols, rows) = match tile_layout(num_players) {
+        Some(layout) => layout,
+        None => return Err(format!("unsupported number of players: {}", num_players)),
+    };
+
+    if width == 0 || height == 0 || !width.is_multiple_of(cols) || !height.is_multiple_of(rows) {
+        return Err(format!("a {}x{} map can't be split evenly between {} players", width, height, num_players));
+    }
+
+    let tile_width = width / cols;
+    let tile_height = height / rows;
+    let tile = generate_tile(&mut seeded_rng(seed), constants, tile_width, tile_height);
+
+    let mut cells = Grid::from_fn(width, height, |position| {
+        let tile_x = mirror(position.x as usize, tile_width);
+        let tile_y = mirror(position.y as usize, tile_height);
+        MapCell::new(position, *tile.get(&Position { x: tile_x as i32, y: tile_y as i32 }))
+    });
+
+    let mut shipyards: Vec<Position> = Vec::with_capacity(num_players);
+    for row in 0..rows {
+        for col in 0..cols {
+            let x = col * tile_width + mirror(col * tile_width + tile_width / 2, tile_width);
+            let y = row * tile_height + mirror(row * tile_height + tile_height / 2, tile_height);
+
+            let position = Position { x: x as i32, y: y as i32 };
+            cells[position].halite = 0;
+            shipyards.push(position);
+        }
+    }
+
+    Ok((GameMap::new(width, height, cells), shipyards))
+}
+
+fn mirror(coordinate: usize, tile_size: usize) -> usize {
+    let local = coordinate % tile_size;
+    if (coordinate / tile_size) % 2 == 1 {
+        tile_size - 1 - local
+    } else {
+        local
+    }
+}
+
+// Fractal value noise, as in the official engine's tile generator: cells are
+// random samples summed over octaves of bilinearly smoothed noise, each octave
+// weighted `persistence` times the previous one, then sharpened and scaled so
+// the richest cell holds between the min and max cell production.
+fn generate_tile(rng: &mut XorShiftRng, constants: &Constants, width: usize, height: usize) -> Grid<usize> {
+    let source = Grid::from_fn(width, height, |_| rng.gen::<f64>().powf(constants.factor_exp_1));
+
+    let max_octave = (min(width, height) as f64).log2().floor() as u32 + 1;
+    let mut region = Grid::new(width, height, 0.0);
+    let mut amplitude = 1.0;
+    for octave in 2..=max_octave {
+        let smoothed = smooth_noise(&source, 1 << (max_octave - octave));
+        region = region.zip_with(&smoothed, |total, value| total + amplitude * value);
+        amplitude *= constants.persistence;
+    }
+
+    let region = region.map(|value| value.powf(constants.factor_exp_2));
+    let max_value = region.iter().cloned().fold(0.0, f64::max);
+    let min_production = constants.min_cell_production as f64;
+    let max_production = constants.max_cell_production as f64;
+    let richest = min_production + rng.gen::<f64>() * (max_production - min_production);
+
+    region.map(|value| {
+        if max_value > 0.0 {
+            (value / max_value * richest).floor() as usize
+        } else {
+            0
+        }
+    })
+}
+
+// Samples every `wavelength`-th cell and interpolates between the samples,
+// wrapping around the tile edges. Like the official engine, the sample grid
+// rounds down, so cells past the last whole wavelength blend into the first
+// samples again.
+fn smooth_noise(source: &Grid<f64>, wavelength: usize) -> Grid<f64> {
+    let samples = Grid::from_fn(
+        source.width() / wavelength,
+        source.height() / wavelength,
+        |position| *source.get(&Position { x: position.x * wavelength as i32, y: position.y * wavelength as i32 }),
+    );
+
+    Grid::from_fn(source.width(), source.height(), |position| {
+        let x = position.x as usize / wavelength;
+        let y = position.y as usize / wavelength;
+        let x_fraction = (position.x as usize % wavelength) as f64 / wavelength as f64;
+        let y_fraction = (position.y as usize % wavelength) as f64 / wavelength as f64;
+
+        let sample = |dx: usize, dy: usize| *samples.get(&Position { x: (x + dx) as i32, y: (y + dy) as i32 });
+        let top = (1.0 - x_fraction) * sample(0, 0) + x_fraction * sample(1, 0);
+        let bottom = (1.0 - x_fraction) * sample(0, 1) + x_fraction * sample(1, 1);
+        (1.0 - y_fraction) * top + y_fraction * bottom
+    })
+}
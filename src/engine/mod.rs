@@ -0,0 +1,179 @@
+pub mod bot;
+pub mod map_gen;
+pub mod replay;
+pub mod state;
+
+use engine::bot::Bot;
+use engine::replay::Replay;
+use engine::state::GameState;
+use hlt::command::Command;
+use hlt::constants::Constants;
+use hlt::PlayerId;
+use std::cmp::max;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+const INIT_TIMEOUT_MILLIS: u64 = 30_000;
+const TURN_TIMEOUT_MILLIS: u64 = 2_000;
+const SHUTDOWN_GRACE_MILLIS: u64 = 1_000;
+
+pub struct EngineConfig {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    pub turn_limit: Option<usize>,
+    pub timeouts: bool,
+    pub strict: bool,
+    pub verbose: bool,
+    pub replay_directory: Option<String>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> EngineConfig {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        EngineConfig {
+            width: 32,
+            height: 32,
+            seed,
+            turn_limit: None,
+            timeouts: true,
+            strict: false,
+            verbose: false,
+            replay_directory: None,
+        }
+    }
+}
+
+pub struct PlayerResult {
+    pub id: PlayerId,
+    pub name: String,
+    pub rank: usize,
+    pub halite: usize,
+    pub last_turn_alive: usize,
+    pub kicked: Option<String>,
+}
+
+pub fn run(config: &EngineConfig, bot_commands: &[String]) -> Result<Vec<PlayerResult>, String> {
+    let mut constants = Constants::default_for(max(config.width, config.height));
+    constants.default_map_width = config.width;
+    constants.default_map_height = config.height;
+    constants.game_seed = config.seed;
+    constants.strict_errors = config.strict;
+    if let Some(turn_limit) = config.turn_limit {
+        constants.max_turns = turn_limit;
+    }
+
+    let (map, shipyards) = map_gen::generate(&constants, config.seed, config.width, config.height, bot_commands.len())?;
+    let mut state = GameState::new(constants, map, &shipyards);
+    let mut replay = config.replay_directory.as_ref().map(|_| Replay::new(&state));
+
+    let mut bots: Vec<Bot> = Vec::with_capacity(bot_commands.len());
+    for command in bot_commands {
+        match Bot::spawn(command) {
+            Ok(bot) => bots.push(bot),
+            Err(e) => return Err(format!("couldn't start '{}': {}", command, e)),
+        }
+    }
+
+    let mut names: Vec<String> = bot_commands.to_vec();
+    let init_timeout = timeout(config, INIT_TIMEOUT_MILLIS);
+    let turn_timeout = timeout(config, TURN_TIMEOUT_MILLIS);
+
+    for (i, bot) in bots.iter_mut().enumerate() {
+        if let Err(e) = bot.send(&state.init_message(PlayerId(i))) {
+            state.kick(PlayerId(i), &format!("couldn't receive the initial state: {}", e));
+        }
+    }
+
+    for (i, bot) in bots.iter_mut().enumerate() {
+        if !state.is_playing(PlayerId(i)) {
+            continue;
+        }
+
+        match bot.receive(init_timeout) {
+            Ok(name) => names[i] = name.trim().to_string(),
+            Err(e) => state.kick(PlayerId(i), &format!("{} during initialization", e)),
+        }
+    }
+
+    while !state.is_finished() {
+        let frame = state.next_frame();
+        let turn_number = state.turn_number;
+
+        for (i, bot) in bots.iter_mut().enumerate() {
+            if !state.is_playing(PlayerId(i)) {
+                continue;
+            }
+
+            if let Err(e) = bot.send(&frame) {
+                state.kick(PlayerId(i), &format!("{} on turn {}", e, turn_number));
+            }
+        }
+
+        let mut commands: Vec<Vec<Command>> = (0..bots.len()).map(|_| Vec::new()).collect();
+        for (i, bot) in bots.iter_mut().enumerate() {
+            if !state.is_playing(PlayerId(i)) {
+                continue;
+            }
+
+            let line = bot.receive(turn_timeout).map_err(|e| e.to_string());
+            match line.and_then(|line| Command::parse_line(&line).map_err(|e| e.to_string())) {
+                Ok(player_commands) => commands[i] = player_commands,
+                Err(e) => state.kick(PlayerId(i), &format!("{} on turn {}", e, turn_number)),
+            }
+        }
+
+        for error in state.process_turn(&commands) {
+            if config.verbose {
+                eprintln!("Turn {}: player {}: {}", turn_number, error.player.0, error.message);
+            }
+
+            if error.fatal || state.constants.strict_errors {
+                state.kick(error.player, &format!("{} on turn {}", error.message, turn_number));
+            }
+        }
+
+        if let Some(ref mut replay) = replay {
+            replay.add_turn(&state, &commands);
+        }
+    }
+
+    for bot in &mut bots {
+        bot.shutdown(Duration::from_millis(SHUTDOWN_GRACE_MILLIS));
+    }
+
+    let results: Vec<PlayerResult> = state
+        .rankings()
+        .iter()
+        .enumerate()
+        .map(|(rank, id)| PlayerResult {
+            id: *id,
+            name: names[id.0].clone(),
+            rank: rank + 1,
+            halite: state.players[id.0].halite,
+            last_turn_alive: state.statuses[id.0].last_turn_alive,
+            kicked: state.statuses[id.0].kicked.clone(),
+        }).collect();
+
+    if let (Some(replay), Some(directory)) = (replay, config.replay_directory.as_ref()) {
+        match replay.save(directory, config.seed, &results) {
+            Ok(path) => println!("Replay written to {}", path.display()),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    Ok(results)
+}
+
+fn timeout(config: &EngineConfig, millis: u64) -> Option<Duration> {
+    if config.timeouts {
+        Some(Duration::from_millis(millis))
+    } else {
+        None
+    }
+}
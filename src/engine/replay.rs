@@ -0,0 +1,111 @@
+use engine::state::GameState;
+use engine::PlayerResult;
+use hlt::command::Command;
+use hlt::json::JsonValue;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+// A record of one game: the starting map plus every command each player sent
+// and each player's bank after every turn. With the seed and constants that is
+// enough to play the game again.
+pub struct Replay {
+    constants: JsonValue,
+    width: usize,
+    height: usize,
+    halite: Vec<usize>,
+    shipyards: Vec<JsonValue>,
+    turns: Vec<JsonValue>,
+}
+
+impl Replay {
+    pub fn new(state: &GameState) -> Replay {
+        // The constants serialize themselves; parsing them back can't fail.
+        let constants = JsonValue::parse(&state.constants.to_json()).unwrap();
+        let shipyards = state
+            .players
+            .iter()
+            .map(|player| {
+                let position = player.shipyard.position;
+                JsonValue::Array(vec![JsonValue::number(position.x), JsonValue::number(position.y)])
+            }).collect();
+
+        Replay {
+            constants,
+            width: state.map.width,
+            height: state.map.height,
+            halite: state.map.cells.iter().map(|cell| cell.halite).collect(),
+            shipyards,
+            turns: Vec::new(),
+        }
+    }
+
+    pub fn add_turn(&mut self, state: &GameState, commands: &[Vec<Command>]) {
+        let commands = commands
+            .iter()
+            .map(|player_commands| {
+                JsonValue::Array(player_commands.iter().map(|command| JsonValue::String(command.to_string())).collect())
+            }).collect();
+        let banks = state.players.iter().map(|player| JsonValue::number(player.halite)).collect();
+
+        self.turns.push(JsonValue::Object(vec![
+            ("turn".to_string(), JsonValue::number(state.turn_number)),
+            ("commands".to_string(), JsonValue::Array(commands)),
+            ("halite".to_string(), JsonValue::Array(banks)),
+        ]));
+    }
+
+    pub fn to_json(&self, results: &[PlayerResult]) -> JsonValue {
+        let players = results
+            .iter()
+            .map(|result| {
+                let kicked = match result.kicked {
+                    Some(ref reason) => JsonValue::String(reason.clone()),
+                    None => JsonValue::Null,
+                };
+                JsonValue::Object(vec![
+                    ("id".to_string(), JsonValue::number(result.id.0)),
+                    ("name".to_string(), JsonValue::String(result.name.clone())),
+                    ("rank".to_string(), JsonValue::number(result.rank)),
+                    ("halite".to_string(), JsonValue::number(result.halite)),
+                    ("last_turn_alive".to_string(), JsonValue::number(result.last_turn_alive)),
+                    ("kicked".to_string(), kicked),
+                ])
+            }).collect();
+
+        let map = JsonValue::Object(vec![
+            ("width".to_string(), JsonValue::number(self.width)),
+            ("height".to_string(), JsonValue::number(self.height)),
+            ("halite".to_string(), JsonValue::Array(self.halite.iter().map(JsonValue::number).collect())),
+            ("shipyards".to_string(), JsonValue::Array(self.shipyards.clone())),
+        ]);
+
+        JsonValue::Object(vec![
+            ("constants".to_string(), self.constants.clone()),
+            ("map".to_string(), map),
+            ("players".to_string(), JsonValue::Array(players)),
+            ("turns".to_string(), JsonValue::Array(self.turns.clone())),
+        ])
+    }
+
+    // Named like the official engine's replays, with the seed and map size.
+    pub fn save(&self, directory: &str, seed: u64, results: &[PlayerResult]) -> Result<PathBuf, String> {
+        if let Err(e) = fs::create_dir_all(directory) {
+            return Err(format!("couldn't create {}: {}", directory, e));
+        }
+
+        let timestamp = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs(),
+            Err(_) => 0,
+        };
+        let filename = format!("replay-{}-{}-{}-{}.json", timestamp, seed, self.width, self.height);
+        let path = Path::new(directory).join(filename);
+
+        match fs::write(&path, format!("{}\n", self.to_json(results))) {
+            Ok(()) => Ok(path),
+            Err(e) => Err(format!("couldn't write {}: {}", path.display(), e)),
+        }
+    }
+}
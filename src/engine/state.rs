@@ -0,0 +1,608 @@
+use hlt::command::Command;
+use hlt::constants::Constants;
+use hlt::direction::Direction;
+use hlt::dropoff::Dropoff;
+use hlt::DropoffId;
+use hlt::game_map::GameMap;
+use hlt::map_cell::Structure;
+use hlt::player::Player;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::ship::Ship;
+use hlt::ShipId;
+use hlt::shipyard::Shipyard;
+use std::cmp::min;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+pub struct CommandError {
+    pub player: PlayerId,
+    pub message: String,
+    pub fatal: bool,
+}
+
+pub struct PlayerStatus {
+    pub kicked: Option<String>,
+    pub last_turn_alive: usize,
+    pub halite_history: Vec<usize>,
+}
+
+pub struct GameState {
+    pub constants: Constants,
+    pub turn_number: usize,
+    pub players: Vec<Player>,
+    pub statuses: Vec<PlayerStatus>,
+    pub ships: HashMap<ShipId, Ship>,
+    pub dropoffs: HashMap<DropoffId, Dropoff>,
+    pub inspired: HashSet<ShipId>,
+    pub map: GameMap,
+    changed_cells: HashSet<Position>,
+    next_ship_id: usize,
+    next_dropoff_id: usize,
+}
+
+impl GameState {
+    pub fn new(constants: Constants, map: GameMap, shipyards: &[Position]) -> GameState {
+        let initial_halite = constants.initial_halite;
+        let mut map = map;
+
+        let mut players: Vec<Player> = Vec::with_capacity(shipyards.len());
+        let mut statuses: Vec<PlayerStatus> = Vec::with_capacity(shipyards.len());
+        for (i, position) in shipyards.iter().enumerate() {
+            let id = PlayerId(i);
+            let shipyard = Shipyard { owner: id, position: *position };
+            map.at_entity_mut(&shipyard).set_structure(Structure::Shipyard(id), id);
+
+            players.push(Player { id, shipyard, halite: initial_halite, ship_ids: Vec::new(), dropoff_ids: Vec::new() });
+            statuses.push(PlayerStatus { kicked: None, last_turn_alive: 0, halite_history: vec![initial_halite] });
+        }
+
+        GameState {
+            constants,
+            turn_number: 0,
+            players,
+            statuses,
+            ships: HashMap::new(),
+            dropoffs: HashMap::new(),
+            inspired: HashSet::new(),
+            map,
+            changed_cells: HashSet::new(),
+            next_ship_id: 0,
+            next_dropoff_id: 0,
+        }
+    }
+
+    pub fn init_message(&self, player_id: PlayerId) -> String {
+        let mut message = String::new();
+        message.push_str(&format!("{}\n", self.constants.to_json()));
+        message.push_str(&format!("{} {}\n", self.players.len(), player_id.0));
+
+        for player in &self.players {
+            let position = player.shipyard.position;
+            message.push_str(&format!("{} {} {}\n", player.id.0, position.x, position.y));
+        }
+
+        message.push_str(&format!("{} {}\n", self.map.width, self.map.height));
+        for row in self.map.cells.rows() {
+            let halite: Vec<String> = row.iter().map(|cell| cell.halite.to_string()).collect();
+            message.push_str(&format!("{}\n", halite.join(" ")));
+        }
+
+        message
+    }
+
+    pub fn next_frame(&mut self) -> String {
+        self.turn_number += 1;
+
+        let mut message = String::new();
+        message.push_str(&format!("{}\n", self.turn_number));
+
+        for player in &self.players {
+            message.push_str(&format!(
+                "{} {} {} {}\n",
+                player.id.0,
+                player.ship_ids.len(),
+                player.dropoff_ids.len(),
+                player.halite));
+
+            for ship_id in &player.ship_ids {
+                let ship = &self.ships[ship_id];
+                message.push_str(&format!("{} {} {} {}\n", ship.id.0, ship.position.x, ship.position.y, ship.halite));
+            }
+
+            for dropoff_id in &player.dropoff_ids {
+                let dropoff = &self.dropoffs[dropoff_id];
+                message.push_str(&format!("{} {} {}\n", dropoff.id.0, dropoff.position.x, dropoff.position.y));
+            }
+        }
+
+        let mut changed_cells: Vec<Position> = self.changed_cells.drain().collect();
+        changed_cells.sort_by_key(|position| (position.y, position.x));
+
+        message.push_str(&format!("{}\n", changed_cells.len()));
+        for position in changed_cells {
+            let halite = self.map.at_position(&position).halite;
+            message.push_str(&format!("{} {} {}\n", position.x, position.y, halite));
+        }
+
+        message
+    }
+
+    pub fn is_playing(&self, player_id: PlayerId) -> bool {
+        let player = &self.players[player_id.0];
+        self.statuses[player_id.0].kicked.is_none()
+            && (!player.ship_ids.is_empty() || player.halite >= self.constants.ship_cost)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        if self.turn_number >= self.constants.max_turns {
+            return true;
+        }
+
+        let playing = self.players.iter().filter(|player| self.is_playing(player.id)).count();
+        playing < min(2, self.players.len())
+    }
+
+    pub fn kick(&mut self, player_id: PlayerId, reason: &str) {
+        if self.statuses[player_id.0].kicked.is_some() {
+            return;
+        }
+
+        self.statuses[player_id.0].kicked = Some(reason.to_string());
+        for ship_id in self.players[player_id.0].ship_ids.clone() {
+            self.remove_ship(ship_id);
+        }
+    }
+
+    pub fn rankings(&self) -> Vec<PlayerId> {
+        let mut rankings: Vec<PlayerId> = self.players.iter().map(|player| player.id).collect();
+        rankings.sort_by(|a, b| {
+            let a = &self.statuses[a.0];
+            let b = &self.statuses[b.0];
+            b.last_turn_alive
+                .cmp(&a.last_turn_alive)
+                .then_with(|| b.halite_history.iter().rev().cmp(a.halite_history.iter().rev()))
+        });
+        rankings
+    }
+
+    pub fn process_turn(&mut self, commands: &[Vec<Command>]) -> Vec<CommandError> {
+        let mut errors: Vec<CommandError> = Vec::new();
+        let mut spawns: Vec<PlayerId> = Vec::new();
+        let mut constructs: Vec<ShipId> = Vec::new();
+        let mut moves: Vec<(ShipId, Direction)> = Vec::new();
+        let mut banks: Vec<(PlayerId, usize)> = Vec::new();
+
+        for player in &self.players {
+            if !self.is_playing(player.id) {
+                continue;
+            }
+
+            let player_commands = match commands.get(player.id.0) {
+                Some(player_commands) => player_commands,
+                None => continue,
+            };
+
+            let mut player_errors: Vec<CommandError> = Vec::new();
+            let mut player_spawns: Vec<PlayerId> = Vec::new();
+            let mut player_constructs: Vec<ShipId> = Vec::new();
+            let mut player_moves: Vec<(ShipId, Direction)> = Vec::new();
+            let mut commanded: HashSet<ShipId> = HashSet::new();
+            let mut bank = player.halite;
+
+            let error = |message: String, fatal: bool| CommandError { player: player.id, message, fatal };
+
+            for command in player_commands {
+                match *command {
+                    Command::Spawn => {
+                        if !player_spawns.is_empty() {
+                            player_errors.push(error("more than one spawn command".to_string(), true));
+                            continue;
+                        }
+
+                        if bank < self.constants.ship_cost {
+                            player_errors.push(error(format!("can't afford a ship with {} halite", bank), false));
+                            continue;
+                        }
+
+                        bank -= self.constants.ship_cost;
+                        player_spawns.push(player.id);
+                    }
+                    Command::Construct(ship_id) | Command::Move(ship_id, _) => {
+                        if !player.ship_ids.contains(&ship_id) {
+                            player_errors.push(error(format!("ship {} is not owned by the player", ship_id.0), true));
+                            continue;
+                        }
+
+                        if !commanded.insert(ship_id) {
+                            player_errors.push(error(format!("more than one command for ship {}", ship_id.0), true));
+                            continue;
+                        }
+
+                        let ship = &self.ships[&ship_id];
+                        let cell = self.map.at_entity(ship);
+
+                        match *command {
+                            Command::Construct(_) => {
+                                if cell.structure.is_some() {
+                                    player_errors.push(error(format!("ship {} can't construct on a structure", ship_id.0), false));
+                                    continue;
+                                }
+
+                                let credit = ship.halite + cell.halite;
+                                if bank + credit < self.constants.dropoff_cost {
+                                    player_errors.push(error(format!("can't afford a dropoff with {} halite", bank + credit), false));
+                                    continue;
+                                }
+
+                                bank = bank + credit - self.constants.dropoff_cost;
+                                player_constructs.push(ship_id);
+                            }
+                            Command::Move(_, Direction::Still) => (),
+                            Command::Move(_, direction) => {
+                                let cost = cell.halite / self.move_cost_ratio(ship_id);
+                                if ship.halite < cost {
+                                    player_errors.push(error(format!("ship {} can't afford to move", ship_id.0), false));
+                                    continue;
+                                }
+
+                                player_moves.push((ship_id, direction));
+                            }
+                            Command::Spawn => unreachable!(),
+                        }
+                    }
+                }
+            }
+
+            // A fatal error voids everything the player sent this turn.
+            if player_errors.iter().all(|error| !error.fatal) {
+                banks.push((player.id, bank));
+                spawns.extend(player_spawns);
+                constructs.extend(player_constructs);
+                moves.extend(player_moves);
+            }
+            errors.extend(player_errors);
+        }
+
+        for (player_id, bank) in banks {
+            self.players[player_id.0].halite = bank;
+        }
+
+        for ship_id in constructs {
+            self.construct_dropoff(ship_id);
+        }
+
+        let mut moved: HashSet<ShipId> = HashSet::new();
+        for (ship_id, direction) in moves {
+            let ratio = self.move_cost_ratio(ship_id);
+            let ship = self.ships.get_mut(&ship_id).unwrap();
+            ship.halite -= self.map.at_position(&ship.position).halite / ratio;
+            ship.position = self.map.torus().offset(&ship.position, direction);
+            moved.insert(ship_id);
+        }
+
+        for player_id in spawns {
+            self.spawn_ship(player_id);
+        }
+
+        self.resolve_collisions();
+        self.deposit_cargo();
+
+        let mut mining: Vec<ShipId> = self.ships.keys().cloned().filter(|id| !moved.contains(id)).collect();
+        mining.sort_by_key(|id| id.0);
+        for ship_id in mining {
+            self.mine(ship_id);
+        }
+
+        self.update_inspiration();
+
+        for player in &self.players {
+            let status = &mut self.statuses[player.id.0];
+            if status.kicked.is_some() {
+                continue;
+            }
+
+            if !player.ship_ids.is_empty() || player.halite >= self.constants.ship_cost {
+                status.last_turn_alive = self.turn_number;
+            }
+            status.halite_history.push(player.halite);
+        }
+
+        errors
+    }
+
+    fn construct_dropoff(&mut self, ship_id: ShipId) {
+        let ship = match self.remove_ship(ship_id) {
+            Some(ship) => ship,
+            None => return,
+        };
+
+        let id = DropoffId(self.next_dropoff_id);
+        self.next_dropoff_id += 1;
+
+        // The cost and the ship's and cell's halite were settled with the
+        // bank when the command was checked.
+        let cell = self.map.at_position_mut(&ship.position);
+        self.players[ship.owner.0].dropoff_ids.push(id);
+
+        cell.halite = 0;
+        cell.set_structure(Structure::Dropoff(id), ship.owner);
+        self.changed_cells.insert(cell.position);
+
+        self.dropoffs.insert(id, Dropoff { owner: ship.owner, id, position: ship.position });
+    }
+
+    fn spawn_ship(&mut self, player_id: PlayerId) {
+        let id = ShipId(self.next_ship_id);
+        self.next_ship_id += 1;
+
+        let player = &mut self.players[player_id.0];
+        let ship = Ship::new(player_id, id, player.shipyard.position, 0, self.constants.max_halite);
+        player.ship_ids.push(id);
+        self.ships.insert(id, ship);
+    }
+
+    // Every ship sharing a cell sinks. Cargo spills into the sea, or to the
+    // structure's owner when the collision happens on a shipyard or dropoff.
+    fn resolve_collisions(&mut self) {
+        let mut occupants: HashMap<Position, Vec<ShipId>> = HashMap::new();
+        for ship in self.ships.values() {
+            occupants.entry(ship.position).or_default().push(ship.id);
+        }
+
+        for (position, ship_ids) in occupants {
+            if ship_ids.len() < 2 {
+                continue;
+            }
+
+            let cargo: usize = ship_ids
+                .iter()
+                .filter_map(|ship_id| self.remove_ship(*ship_id))
+                .map(|ship| ship.halite)
+                .sum();
+
+            match self.structure_owner(&position) {
+                Some(owner) => self.players[owner.0].halite += cargo,
+                None => {
+                    self.map.at_position_mut(&position).halite += cargo;
+                    self.changed_cells.insert(position);
+                }
+            }
+        }
+    }
+
+    fn deposit_cargo(&mut self) {
+        let mut deposits: Vec<ShipId> = Vec::new();
+        for ship in self.ships.values() {
+            if ship.halite > 0 && self.structure_owner(&ship.position) == Some(ship.owner) {
+                deposits.push(ship.id);
+            }
+        }
+
+        for ship_id in deposits {
+            let ship = self.ships.get_mut(&ship_id).unwrap();
+            self.players[ship.owner.0].halite += ship.halite;
+            ship.halite = 0;
+        }
+    }
+
+    fn mine(&mut self, ship_id: ShipId) {
+        let inspired = self.inspired.contains(&ship_id);
+        let ratio = if inspired { self.constants.inspired_extract_ratio } else { self.constants.extract_ratio };
+        let max_halite = self.constants.max_halite;
+
+        let ship = self.ships.get_mut(&ship_id).unwrap();
+        let cell = self.map.at_position_mut(&ship.position);
+
+        let extracted = min(cell.halite.div_ceil(ratio), max_halite - ship.halite);
+        if extracted == 0 {
+            return;
+        }
+
+        cell.halite -= extracted;
+        ship.halite += extracted;
+        if inspired {
+            let bonus = (extracted as f64 * self.constants.inspired_bonus_multiplier) as usize;
+            ship.halite = min(max_halite, ship.halite + bonus);
+        }
+
+        self.changed_cells.insert(cell.position);
+    }
+
+    // Inspiration is decided from the positions at the end of a turn and
+    // applies to mining and moving during the next one.
+    fn update_inspiration(&mut self) {
+        self.inspired.clear();
+        if !self.constants.inspiration_enabled {
+            return;
+        }
+
+        for ship in self.ships.values() {
+            let nearby_enemies = self
+                .ships
+                .values()
+                .filter(|other| other.owner != ship.owner)
+                .filter(|other| self.map.calculate_distance(&ship.position, &other.position) <= self.constants.inspiration_radius)
+                .count();
+
+            if nearby_enemies >= self.constants.inspiration_ship_count {
+                self.inspired.insert(ship.id);
+            }
+        }
+    }
+
+    fn structure_owner(&self, position: &Position) -> Option<PlayerId> {
+        self.map.at_position(position).structure_owner
+    }
+
+    fn move_cost_ratio(&self, ship_id: ShipId) -> usize {
+        if self.inspired.contains(&ship_id) {
+            self.constants.inspired_move_cost_ratio
+        } else {
+            self.constants.move_cost_ratio
+        }
+    }
+
+    fn remove_ship(&mut self, ship_id: ShipId) -> Option<Ship> {
+        let ship = self.ships.remove(&ship_id)?;
+        self.players[ship.owner.0].ship_ids.retain(|id| *id != ship_id);
+        self.inspired.remove(&ship_id);
+        Some(ship)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::fixtures;
+    use hlt::fixtures::position;
+
+    // Player 0's shipyard is at (2, 2) and player 1's at (12, 12) on an
+    // otherwise empty 16x16 map.
+    fn state() -> GameState {
+        let map = fixtures::map(16, 16, |_| 0);
+        GameState::new(Constants::default_for(16), map, &[position(2, 2), position(12, 12)])
+    }
+
+    fn add_ship(state: &mut GameState, owner: usize, x: i32, y: i32, halite: usize) -> ShipId {
+        state.spawn_ship(PlayerId(owner));
+        let id = ShipId(state.next_ship_id - 1);
+        let ship = state.ships.get_mut(&id).unwrap();
+        ship.position = position(x, y);
+        ship.halite = halite;
+        id
+    }
+
+    fn set_halite(state: &mut GameState, x: i32, y: i32, halite: usize) {
+        state.map.at_position_mut(&position(x, y)).halite = halite;
+    }
+
+    #[test]
+    fn constructing_a_dropoff_settles_the_bank_once() {
+        let mut state = state();
+        let ship_id = add_ship(&mut state, 0, 5, 5, 500);
+        set_halite(&mut state, 5, 5, 100);
+
+        let errors = state.process_turn(&[vec![Command::Construct(ship_id)], Vec::new()]);
+
+        assert!(errors.is_empty());
+        assert_eq!(state.players[0].halite, 5000 + 500 + 100 - 4000);
+        assert!(state.ships.is_empty());
+        assert_eq!(state.players[0].dropoff_ids.len(), 1);
+        let cell = state.map.at_position(&position(5, 5));
+        assert_eq!(cell.halite, 0);
+        assert_eq!(cell.structure_owner, Some(PlayerId(0)));
+    }
+
+    #[test]
+    fn spawning_costs_a_ship() {
+        let mut state = state();
+        let errors = state.process_turn(&[vec![Command::Spawn], Vec::new()]);
+
+        assert!(errors.is_empty());
+        assert_eq!(state.players[0].halite, 4000);
+        let ship = &state.ships[&state.players[0].ship_ids[0]];
+        assert_eq!((ship.position, ship.halite), (position(2, 2), 0));
+    }
+
+    #[test]
+    fn moving_costs_a_fraction_of_the_cell_rounded_down() {
+        let mut state = state();
+        state.constants.inspired_move_cost_ratio = 20;
+        let plain = add_ship(&mut state, 0, 5, 5, 50);
+        let inspired = add_ship(&mut state, 0, 5, 9, 50);
+        set_halite(&mut state, 5, 5, 109);
+        set_halite(&mut state, 5, 9, 109);
+        state.inspired.insert(inspired);
+
+        state.process_turn(&[vec![Command::Move(plain, Direction::East), Command::Move(inspired, Direction::East)], Vec::new()]);
+
+        assert_eq!(state.ships[&plain].halite, 40);
+        assert_eq!(state.ships[&plain].position, position(6, 5));
+        assert_eq!(state.ships[&inspired].halite, 45);
+    }
+
+    #[test]
+    fn mining_takes_a_fraction_of_the_cell_rounded_up() {
+        let mut state = state();
+        let plain = add_ship(&mut state, 0, 5, 5, 0);
+        let inspired = add_ship(&mut state, 0, 5, 9, 0);
+        set_halite(&mut state, 5, 5, 101);
+        set_halite(&mut state, 5, 9, 101);
+        state.inspired.insert(inspired);
+
+        state.process_turn(&[Vec::new(), Vec::new()]);
+
+        assert_eq!(state.ships[&plain].halite, 26);
+        assert_eq!(state.map.at_position(&position(5, 5)).halite, 75);
+        // The bonus comes on top and isn't taken from the cell.
+        assert_eq!(state.ships[&inspired].halite, 26 * 3);
+        assert_eq!(state.map.at_position(&position(5, 9)).halite, 75);
+    }
+
+    #[test]
+    fn ships_near_enough_enemies_are_inspired() {
+        let mut state = state();
+        let ship_id = add_ship(&mut state, 0, 5, 5, 0);
+        add_ship(&mut state, 1, 7, 5, 0);
+        add_ship(&mut state, 1, 5, 8, 0);
+        let lonely = add_ship(&mut state, 0, 14, 1, 0);
+
+        state.update_inspiration();
+
+        assert!(state.inspired.contains(&ship_id));
+        assert!(!state.inspired.contains(&lonely));
+    }
+
+    #[test]
+    fn collisions_spill_cargo_into_the_sea() {
+        let mut state = state();
+        let a = add_ship(&mut state, 0, 5, 5, 100);
+        let b = add_ship(&mut state, 1, 7, 5, 200);
+
+        state.process_turn(&[vec![Command::Move(a, Direction::East)], vec![Command::Move(b, Direction::West)]]);
+
+        assert!(state.ships.is_empty());
+        assert_eq!(state.map.at_position(&position(6, 5)).halite, 300);
+        assert!(state.next_frame().ends_with("1\n6 5 300\n"));
+    }
+
+    #[test]
+    fn collisions_on_a_structure_go_to_its_owner() {
+        let mut state = state();
+        let ours = add_ship(&mut state, 0, 3, 2, 100);
+        let theirs = add_ship(&mut state, 1, 2, 3, 300);
+
+        state.process_turn(&[vec![Command::Move(ours, Direction::West)], vec![Command::Move(theirs, Direction::North)]]);
+
+        assert!(state.ships.is_empty());
+        assert_eq!(state.players[0].halite, 5400);
+        assert_eq!(state.players[1].halite, 5000);
+        assert_eq!(state.map.at_position(&position(2, 2)).halite, 0);
+    }
+
+    #[test]
+    fn ships_deposit_on_their_own_structures() {
+        let mut state = state();
+        let ship_id = add_ship(&mut state, 0, 2, 3, 300);
+        let visitor = add_ship(&mut state, 1, 11, 12, 300);
+
+        state.process_turn(&[vec![Command::Move(ship_id, Direction::North)], vec![Command::Move(visitor, Direction::East)]]);
+
+        assert_eq!(state.players[0].halite, 5300);
+        assert_eq!(state.ships[&ship_id].halite, 0);
+        // Player 1 owns (12, 12), so this one deposits too.
+        assert_eq!(state.players[1].halite, 5300);
+    }
+
+    #[test]
+    fn deposits_only_count_on_your_own_structures() {
+        let mut state = state();
+        let ship_id = add_ship(&mut state, 1, 2, 3, 300);
+
+        state.process_turn(&[Vec::new(), vec![Command::Move(ship_id, Direction::North)]]);
+
+        assert_eq!(state.ships[&ship_id].halite, 300);
+        assert_eq!((state.players[0].halite, state.players[1].halite), (5000, 5000));
+    }
+}
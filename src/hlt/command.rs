@@ -1,18 +1,141 @@
 use hlt::direction::Direction;
 use hlt::ShipId;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
-pub struct Command(pub String);
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Command {
+    Spawn,
+    Construct(ShipId),
+    Move(ShipId, Direction),
+}
+
+#[derive(Debug)]
+pub struct ParseCommandError(pub String);
 
 impl Command {
     pub fn spawn_ship() -> Command {
-        Command(format!("g"))
+        Command::Spawn
     }
 
     pub fn transform_ship_into_dropoff_site(ship_id: ShipId) -> Command {
-        Command(format!("c {}", ship_id.0))
+        Command::Construct(ship_id)
     }
 
     pub fn move_ship(ship_id: ShipId, direction: Direction) -> Command {
-        Command(format!("m {} {}", ship_id.0, direction.get_char_encoding()))
+        Command::Move(ship_id, direction)
+    }
+
+    pub fn ship_id(&self) -> Option<ShipId> {
+        match *self {
+            Command::Spawn => None,
+            Command::Construct(ship_id) | Command::Move(ship_id, _) => Some(ship_id),
+        }
+    }
+
+    // Splits a whole turn as sent to the engine, e.g. "g m 3 n c 5".
+    pub fn parse_line(line: &str) -> Result<Vec<Command>, ParseCommandError> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let mut commands: Vec<Command> = Vec::new();
+
+        let mut i = 0;
+        while i < tokens.len() {
+            let length = match tokens[i] {
+                "g" => 1,
+                "c" => 2,
+                "m" => 3,
+                token => return Err(ParseCommandError(format!("unknown command '{}'", token))),
+            };
+
+            if i + length > tokens.len() {
+                return Err(ParseCommandError(format!("incomplete command '{}'", tokens[i..].join(" "))));
+            }
+
+            commands.push(tokens[i..i + length].join(" ").parse()?);
+            i += length;
+        }
+
+        Ok(commands)
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Spawn => write!(f, "g"),
+            Command::Construct(ship_id) => write!(f, "c {}", ship_id.0),
+            Command::Move(ship_id, direction) => write!(f, "m {} {}", ship_id.0, direction.get_char_encoding()),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = ParseCommandError;
+
+    fn from_str(s: &str) -> Result<Command, ParseCommandError> {
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+        let parse_ship_id = |token: &str| {
+            token.parse().map(ShipId).map_err(|_| ParseCommandError(format!("invalid ship id '{}' in '{}'", token, s)))
+        };
+
+        match tokens.as_slice() {
+            ["g"] => Ok(Command::Spawn),
+            ["c", ship_id] => Ok(Command::Construct(parse_ship_id(ship_id)?)),
+            ["m", ship_id, direction] => {
+                let mut chars = direction.chars();
+                let direction = match (chars.next().and_then(Direction::from_char), chars.next()) {
+                    (Some(direction), None) => direction,
+                    _ => return Err(ParseCommandError(format!("invalid direction '{}' in '{}'", direction, s))),
+                };
+                Ok(Command::Move(parse_ship_id(ship_id)?, direction))
+            }
+            _ => Err(ParseCommandError(format!("invalid command '{}'", s))),
+        }
+    }
+}
+
+impl fmt::Display for ParseCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ParseCommandError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_each_command() {
+        for &text in &["m 3 n", "m 0 o", "m 12 w", "c 5", "g"] {
+            let command: Command = text.parse().unwrap();
+            assert_eq!(command.to_string(), text);
+        }
+        assert_eq!("m 3 n".parse::<Command>().unwrap(), Command::Move(ShipId(3), Direction::North));
+        assert_eq!("c 5".parse::<Command>().unwrap(), Command::Construct(ShipId(5)));
+        assert_eq!("g".parse::<Command>().unwrap(), Command::Spawn);
+    }
+
+    #[test]
+    fn rejects_malformed_commands() {
+        for &text in &["m 3 x", "m 3 nn", "c", "m a n", "m 3", "g 1", "c 5 6", "m 3 n s", "x", ""] {
+            assert!(text.parse::<Command>().is_err(), "'{}' parsed", text);
+        }
+    }
+
+    #[test]
+    fn parses_a_whole_turn() {
+        let commands = Command::parse_line("g m 3 n  c 5\n").unwrap();
+        assert_eq!(commands, vec![Command::Spawn, Command::Move(ShipId(3), Direction::North), Command::Construct(ShipId(5))]);
+        assert_eq!(Command::parse_line("").unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn rejects_a_malformed_turn() {
+        for &line in &["m 3 n x", "g c", "m 3", "m 3 x g", "c a"] {
+            assert!(Command::parse_line(line).is_err(), "'{}' parsed", line);
+        }
     }
 }
@@ -1,4 +1,8 @@
+use hlt::error::ProtocolError;
+use hlt::error::ProtocolErrorKind;
+use hlt::json::JsonValue;
 use hlt::log::Log;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::str::FromStr;
 
@@ -15,52 +19,306 @@ pub struct Constants {
     pub inspired_extract_ratio: usize,
     pub inspired_bonus_multiplier: f64,
     pub inspired_move_cost_ratio: usize,
+    pub initial_halite: usize,
+    pub capture_enabled: bool,
+    pub capture_radius: usize,
+    pub ships_above_for_capture: usize,
+    pub default_map_width: usize,
+    pub default_map_height: usize,
+    pub dropoff_penalty_ratio: usize,
+    pub factor_exp_1: f64,
+    pub factor_exp_2: f64,
+    pub max_cell_production: usize,
+    pub min_cell_production: usize,
+    pub max_players: usize,
+    pub min_turns: usize,
+    pub min_turn_threshold: usize,
+    pub max_turn_threshold: usize,
+    pub persistence: f64,
+    pub strict_errors: bool,
+    pub game_seed: u64,
+    pub extra: BTreeMap<String, JsonValue>,
+    // The values as the engine sent them, so unchanged numbers are written
+    // back with the same text.
+    source: BTreeMap<String, JsonValue>,
 }
 
+// Keys and values of the engine's JSON, tagged with their entry index.
+type Entries = HashMap<String, (JsonValue, usize)>;
+
+const DEFAULT_MIN_TURNS: usize = 400;
+const DEFAULT_MAX_TURNS: usize = 500;
+const DEFAULT_MIN_TURN_THRESHOLD: usize = 32;
+const DEFAULT_MAX_TURN_THRESHOLD: usize = 64;
+const DEFAULT_MAX_PLAYERS: usize = 16;
+
 impl Constants {
     pub fn new(string_from_engine: &str) -> Constants {
-        let token_iter = string_from_engine.split(|c| " {},:\"\r\n".contains(c));
-        let token_iter = token_iter.filter(|x| !x.is_empty());
-        let tokens: Vec<&str> = token_iter.collect();
-
-        if (tokens.len() % 2) != 0 {
-            Log::panic("Error: constants: expected even total number of key and value tokens from server.");
+        match Constants::try_new(string_from_engine) {
+            Ok(constants) => constants,
+            Err(e) => Log::panic(&format!("Error: constants: {}.", e)),
         }
+    }
 
-        let mut map = HashMap::new();
+    // The constants are always the first line sent by the engine. Errors
+    // carry no line number; the caller knows where the text came from.
+    pub fn try_new(string_from_engine: &str) -> Result<Constants, ProtocolError> {
+        let entries = match JsonValue::parse(string_from_engine) {
+            Ok(JsonValue::Object(entries)) => entries,
+            Ok(_) => return Err(Constants::malformed("expected an object".to_string())),
+            Err(e) => return Err(Constants::malformed(e)),
+        };
 
-        for i in (0..tokens.len()).step_by(2) {
-            map.insert(tokens[i].to_string(), tokens[i+1].to_string());
+        let source: BTreeMap<String, JsonValue> = entries.iter().cloned().collect();
+        let mut map: Entries = HashMap::new();
+        for (i, (key, value)) in entries.into_iter().enumerate() {
+            map.insert(key, (value, i));
         }
 
+        // Keys the engine leaves out take the values it would have sent.
+        let width = map.get("DEFAULT_MAP_WIDTH").and_then(|(value, _)| value.as_usize()).unwrap_or(32);
+        let defaults = Constants::default_for(width);
+
+        let constants = Constants {
+            ship_cost: Constants::get_value(&mut map, "NEW_ENTITY_ENERGY_COST", defaults.ship_cost)?,
+            dropoff_cost: Constants::get_value(&mut map, "DROPOFF_COST", defaults.dropoff_cost)?,
+            max_halite: Constants::get_value(&mut map, "MAX_ENERGY", defaults.max_halite)?,
+            max_turns: Constants::get_value(&mut map, "MAX_TURNS", defaults.max_turns)?,
+            extract_ratio: Constants::get_value(&mut map, "EXTRACT_RATIO", defaults.extract_ratio)?,
+            move_cost_ratio: Constants::get_value(&mut map, "MOVE_COST_RATIO", defaults.move_cost_ratio)?,
+            inspiration_enabled: Constants::get_value(&mut map, "INSPIRATION_ENABLED", defaults.inspiration_enabled)?,
+            inspiration_radius: Constants::get_value(&mut map, "INSPIRATION_RADIUS", defaults.inspiration_radius)?,
+            inspiration_ship_count: Constants::get_value(&mut map, "INSPIRATION_SHIP_COUNT", defaults.inspiration_ship_count)?,
+            inspired_extract_ratio: Constants::get_value(&mut map, "INSPIRED_EXTRACT_RATIO", defaults.inspired_extract_ratio)?,
+            inspired_bonus_multiplier: Constants::get_value(&mut map, "INSPIRED_BONUS_MULTIPLIER", defaults.inspired_bonus_multiplier)?,
+            inspired_move_cost_ratio: Constants::get_value(&mut map, "INSPIRED_MOVE_COST_RATIO", defaults.inspired_move_cost_ratio)?,
+            initial_halite: Constants::get_value(&mut map, "INITIAL_ENERGY", defaults.initial_halite)?,
+            capture_enabled: Constants::get_value(&mut map, "CAPTURE_ENABLED", defaults.capture_enabled)?,
+            capture_radius: Constants::get_value(&mut map, "CAPTURE_RADIUS", defaults.capture_radius)?,
+            ships_above_for_capture: Constants::get_value(&mut map, "SHIPS_ABOVE_FOR_CAPTURE", defaults.ships_above_for_capture)?,
+            default_map_width: Constants::get_value(&mut map, "DEFAULT_MAP_WIDTH", defaults.default_map_width)?,
+            default_map_height: Constants::get_value(&mut map, "DEFAULT_MAP_HEIGHT", defaults.default_map_height)?,
+            dropoff_penalty_ratio: Constants::get_value(&mut map, "DROPOFF_PENALTY_RATIO", defaults.dropoff_penalty_ratio)?,
+            factor_exp_1: Constants::get_value(&mut map, "FACTOR_EXP_1", defaults.factor_exp_1)?,
+            factor_exp_2: Constants::get_value(&mut map, "FACTOR_EXP_2", defaults.factor_exp_2)?,
+            max_cell_production: Constants::get_value(&mut map, "MAX_CELL_PRODUCTION", defaults.max_cell_production)?,
+            min_cell_production: Constants::get_value(&mut map, "MIN_CELL_PRODUCTION", defaults.min_cell_production)?,
+            max_players: Constants::get_value(&mut map, "MAX_PLAYERS", defaults.max_players)?,
+            min_turns: Constants::get_value(&mut map, "MIN_TURNS", defaults.min_turns)?,
+            min_turn_threshold: Constants::get_value(&mut map, "MIN_TURN_THRESHOLD", defaults.min_turn_threshold)?,
+            max_turn_threshold: Constants::get_value(&mut map, "MAX_TURN_THRESHOLD", defaults.max_turn_threshold)?,
+            persistence: Constants::get_value(&mut map, "PERSISTENCE", defaults.persistence)?,
+            strict_errors: Constants::get_value(&mut map, "STRICT_ERRORS", defaults.strict_errors)?,
+            game_seed: Constants::get_value(&mut map, "game_seed", defaults.game_seed)?,
+            extra: map.into_iter().map(|(key, (value, _))| (key, value)).collect(),
+            source,
+        };
+
+        Ok(constants)
+    }
+
+    // Matches what the official engine sends for a map of the given width.
+    pub fn default_for(width: usize) -> Constants {
+        let size = width.clamp(DEFAULT_MIN_TURN_THRESHOLD, DEFAULT_MAX_TURN_THRESHOLD);
+        let max_turns = DEFAULT_MIN_TURNS
+            + (size - DEFAULT_MIN_TURN_THRESHOLD) * (DEFAULT_MAX_TURNS - DEFAULT_MIN_TURNS)
+                / (DEFAULT_MAX_TURN_THRESHOLD - DEFAULT_MIN_TURN_THRESHOLD);
+
         Constants {
-            ship_cost: Constants::get_value(&map, "NEW_ENTITY_ENERGY_COST"),
-            dropoff_cost: Constants::get_value(&map, "DROPOFF_COST"),
-            max_halite: Constants::get_value(&map, "MAX_ENERGY"),
-            max_turns: Constants::get_value(&map, "MAX_TURNS"),
-            extract_ratio: Constants::get_value(&map, "EXTRACT_RATIO"),
-            move_cost_ratio: Constants::get_value(&map, "MOVE_COST_RATIO"),
-            inspiration_enabled: Constants::get_value(&map, "INSPIRATION_ENABLED"),
-            inspiration_radius: Constants::get_value(&map, "INSPIRATION_RADIUS"),
-            inspiration_ship_count: Constants::get_value(&map, "INSPIRATION_SHIP_COUNT"),
-            inspired_extract_ratio: Constants::get_value(&map, "INSPIRED_EXTRACT_RATIO"),
-            inspired_bonus_multiplier: Constants::get_value(&map, "INSPIRED_BONUS_MULTIPLIER"),
-            inspired_move_cost_ratio: Constants::get_value(&map, "INSPIRED_MOVE_COST_RATIO"),
+            max_halite: 1000,
+            ship_cost: 1000,
+            dropoff_cost: 4000,
+            max_turns,
+            extract_ratio: 4,
+            move_cost_ratio: 10,
+            inspiration_enabled: true,
+            inspiration_radius: 4,
+            inspiration_ship_count: 2,
+            inspired_extract_ratio: 4,
+            inspired_bonus_multiplier: 2.0,
+            inspired_move_cost_ratio: 10,
+            initial_halite: 5000,
+            capture_enabled: false,
+            capture_radius: 3,
+            ships_above_for_capture: 3,
+            default_map_width: width,
+            default_map_height: width,
+            dropoff_penalty_ratio: 4,
+            factor_exp_1: 2.0,
+            factor_exp_2: 2.0,
+            max_cell_production: 1000,
+            min_cell_production: 900,
+            max_players: DEFAULT_MAX_PLAYERS,
+            min_turns: DEFAULT_MIN_TURNS,
+            min_turn_threshold: DEFAULT_MIN_TURN_THRESHOLD,
+            max_turn_threshold: DEFAULT_MAX_TURN_THRESHOLD,
+            persistence: 0.7,
+            strict_errors: false,
+            game_seed: 0,
+            extra: BTreeMap::new(),
+            source: BTreeMap::new(),
         }
     }
 
-    fn get_value<T: FromStr>(map: &HashMap<String, String>, key: &str) -> T {
-        let s = Constants::get_string(map, key);
-        match s.parse::<T>() {
-            Ok(x) => x,
-            Err(_) => Log::panic(&format!("Error: constants: for {} got '{}' from server and failed to parse that.", key, s))
-        }
+    // Keys come out sorted, the same order the engine uses.
+    pub fn to_json(&self) -> String {
+        let mut entries: BTreeMap<String, JsonValue> = self.extra.clone();
+        let source = &self.source;
+        let mut insert = |key: &str, value: JsonValue| {
+            let value = match source.get(key) {
+                Some(original) if same_value(original, &value) => original.clone(),
+                _ => value,
+            };
+            entries.insert(key.to_string(), value);
+        };
+
+        insert("NEW_ENTITY_ENERGY_COST", JsonValue::number(self.ship_cost));
+        insert("DROPOFF_COST", JsonValue::number(self.dropoff_cost));
+        insert("MAX_ENERGY", JsonValue::number(self.max_halite));
+        insert("MAX_TURNS", JsonValue::number(self.max_turns));
+        insert("EXTRACT_RATIO", JsonValue::number(self.extract_ratio));
+        insert("MOVE_COST_RATIO", JsonValue::number(self.move_cost_ratio));
+        insert("INSPIRATION_ENABLED", JsonValue::Bool(self.inspiration_enabled));
+        insert("INSPIRATION_RADIUS", JsonValue::number(self.inspiration_radius));
+        insert("INSPIRATION_SHIP_COUNT", JsonValue::number(self.inspiration_ship_count));
+        insert("INSPIRED_EXTRACT_RATIO", JsonValue::number(self.inspired_extract_ratio));
+        insert("INSPIRED_BONUS_MULTIPLIER", JsonValue::float(self.inspired_bonus_multiplier));
+        insert("INSPIRED_MOVE_COST_RATIO", JsonValue::number(self.inspired_move_cost_ratio));
+        insert("INITIAL_ENERGY", JsonValue::number(self.initial_halite));
+        insert("CAPTURE_ENABLED", JsonValue::Bool(self.capture_enabled));
+        insert("CAPTURE_RADIUS", JsonValue::number(self.capture_radius));
+        insert("SHIPS_ABOVE_FOR_CAPTURE", JsonValue::number(self.ships_above_for_capture));
+        insert("DEFAULT_MAP_WIDTH", JsonValue::number(self.default_map_width));
+        insert("DEFAULT_MAP_HEIGHT", JsonValue::number(self.default_map_height));
+        insert("DROPOFF_PENALTY_RATIO", JsonValue::number(self.dropoff_penalty_ratio));
+        insert("FACTOR_EXP_1", JsonValue::float(self.factor_exp_1));
+        insert("FACTOR_EXP_2", JsonValue::float(self.factor_exp_2));
+        insert("MAX_CELL_PRODUCTION", JsonValue::number(self.max_cell_production));
+        insert("MIN_CELL_PRODUCTION", JsonValue::number(self.min_cell_production));
+        insert("MAX_PLAYERS", JsonValue::number(self.max_players));
+        insert("MIN_TURNS", JsonValue::number(self.min_turns));
+        insert("MIN_TURN_THRESHOLD", JsonValue::number(self.min_turn_threshold));
+        insert("MAX_TURN_THRESHOLD", JsonValue::number(self.max_turn_threshold));
+        insert("PERSISTENCE", JsonValue::float(self.persistence));
+        insert("STRICT_ERRORS", JsonValue::Bool(self.strict_errors));
+        insert("game_seed", JsonValue::number(self.game_seed));
+
+        JsonValue::Object(entries.into_iter().collect()).to_string()
     }
 
-    fn get_string<'a>(map: &'a HashMap<String, String>, key: &str) -> &'a String {
-        match map.get(key) {
+    fn malformed(message: String) -> ProtocolError {
+        ProtocolError::without_line(ProtocolErrorKind::MalformedConstants(message), None)
+    }
+
+    fn get_value<T: FromStr>(map: &mut Entries, key: &str, default: T) -> Result<T, ProtocolError> {
+        let (value, token) = match map.remove(key) {
             Some(x) => x,
-            None => Log::panic(&format!("Error: constants: server did not send {} constant.", key))
+            None => return Ok(default),
+        };
+
+        let parsed = match &value {
+            JsonValue::Number(s) => s.parse::<T>().ok(),
+            JsonValue::Bool(b) => b.to_string().parse::<T>().ok(),
+            _ => None,
+        };
+
+        match parsed {
+            Some(x) => Ok(x),
+            None => {
+                let kind = ProtocolErrorKind::InvalidConstant(key.to_string(), value.to_string());
+                Err(ProtocolError::without_line(kind, Some(token)))
+            }
         }
     }
 }
+
+// `2` and `2.0` are the same number.
+fn same_value(a: &JsonValue, b: &JsonValue) -> bool {
+    match (a, b) {
+        (JsonValue::Number(a), JsonValue::Number(b)) => match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => a == b,
+        },
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::game::Game;
+    use std::io;
+
+    // As sent by the official engine for a 48x48 map.
+    const ENGINE_LINE: &str = "{\"CAPTURE_ENABLED\":false,\"CAPTURE_RADIUS\":3,\"DEFAULT_MAP_HEIGHT\":48,\"DEFAULT_MAP_WIDTH\":48,\"DROPOFF_COST\":4000,\"DROPOFF_PENALTY_RATIO\":4,\"EXTRACT_RATIO\":4,\"FACTOR_EXP_1\":2.0,\"FACTOR_EXP_2\":2.0,\"INITIAL_ENERGY\":5000,\"INSPIRATION_ENABLED\":true,\"INSPIRATION_RADIUS\":4,\"INSPIRATION_SHIP_COUNT\":2,\"INSPIRED_BONUS_MULTIPLIER\":2.0,\"INSPIRED_EXTRACT_RATIO\":4,\"INSPIRED_MOVE_COST_RATIO\":10,\"MAX_CELL_PRODUCTION\":1000,\"MAX_ENERGY\":1000,\"MAX_PLAYERS\":16,\"MAX_TURNS\":450,\"MAX_TURN_THRESHOLD\":64,\"MIN_CELL_PRODUCTION\":900,\"MIN_TURNS\":400,\"MIN_TURN_THRESHOLD\":32,\"MOVE_COST_RATIO\":10,\"NEW_ENTITY_ENERGY_COST\":1000,\"PERSISTENCE\":0.7,\"SHIPS_ABOVE_FOR_CAPTURE\":3,\"STRICT_ERRORS\":false,\"game_seed\":1540483461}";
+
+    #[test]
+    fn parses_an_engine_line() {
+        let constants = Constants::try_new(ENGINE_LINE).unwrap();
+        assert_eq!(constants.max_turns, 450);
+        assert_eq!(constants.ship_cost, 1000);
+        assert_eq!(constants.persistence, 0.7);
+        assert!(constants.inspiration_enabled);
+        assert!(!constants.capture_enabled);
+        assert_eq!(constants.game_seed, 1540483461);
+        assert!(constants.extra.is_empty());
+    }
+
+    #[test]
+    fn round_trips_an_engine_line() {
+        assert_eq!(Constants::try_new(ENGINE_LINE).unwrap().to_json(), ENGINE_LINE);
+    }
+
+    #[test]
+    fn keeps_integer_floats_as_sent() {
+        let line = ENGINE_LINE.replace("\"FACTOR_EXP_1\":2.0", "\"FACTOR_EXP_1\":2");
+        let constants = Constants::try_new(&line).unwrap();
+        assert_eq!(constants.factor_exp_1, 2.0);
+        assert_eq!(constants.to_json(), line);
+    }
+
+    #[test]
+    fn changed_values_are_written_fresh() {
+        let mut constants = Constants::try_new(ENGINE_LINE).unwrap();
+        constants.max_turns = 10;
+        assert!(constants.to_json().contains("\"MAX_TURNS\":10,"));
+    }
+
+    #[test]
+    fn keeps_unknown_keys() {
+        let line = ENGINE_LINE.replace("{", "{\"A_NEW_KEY\":[1,2.50],");
+        let constants = Constants::try_new(&line).unwrap();
+        assert_eq!(constants.extra.len(), 1);
+        assert_eq!(constants.to_json(), line);
+    }
+
+    #[test]
+    fn missing_keys_take_the_defaults() {
+        let constants = Constants::try_new("{\"DEFAULT_MAP_WIDTH\":64,\"MAX_ENERGY\":500}").unwrap();
+        let defaults = Constants::default_for(64);
+        assert_eq!(constants.max_halite, 500);
+        assert_eq!(constants.max_turns, defaults.max_turns);
+        assert_eq!(constants.ship_cost, defaults.ship_cost);
+        assert_eq!(constants.persistence, defaults.persistence);
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_type() {
+        let line = ENGINE_LINE.replace("\"MAX_TURNS\":450", "\"MAX_TURNS\":\"many\"");
+        assert!(Constants::try_new(&line).is_err());
+        assert!(Constants::try_new("[1, 2]").is_err());
+        assert!(Constants::try_new("{\"MAX_TURNS\":").is_err());
+    }
+
+    #[test]
+    fn errors_point_at_the_entry() {
+        let line = ENGINE_LINE.replace("\"MAX_TURNS\":450", "\"MAX_TURNS\":\"many\"");
+        let error = Constants::try_new(&line).err().unwrap();
+        assert_eq!((error.line, error.token), (None, Some(19)));
+
+        // Read from the engine, the line is known.
+        let input = io::Cursor::new(format!("{}\n", line).into_bytes());
+        let error = Game::try_with_io(input, io::sink()).err().unwrap();
+        assert_eq!((error.line, error.token), (Some(1), Some(19)));
+    }
+}
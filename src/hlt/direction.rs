@@ -35,4 +35,15 @@ impl Direction {
             Direction::Still => 'o',
         }
     }
+
+    pub fn from_char(c: char) -> Option<Direction> {
+        match c {
+            'n' => Some(Direction::North),
+            'e' => Some(Direction::East),
+            's' => Some(Direction::South),
+            'w' => Some(Direction::West),
+            'o' => Some(Direction::Still),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,195 @@
+use hlt::direction::Direction;
+use hlt::game::Game;
+use hlt::grid::Grid;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::torus::Torus;
+use std::collections::VecDeque;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Route {
+    pub base: Position,
+    pub distance: usize,
+    pub first_step: Direction,
+}
+
+// Breadth-first distances from a set of bases to every cell. Ties between
+// bases go to the one listed first.
+pub struct DistanceField {
+    sources: Vec<Position>,
+    routes: Grid<Option<Route>>,
+}
+
+impl DistanceField {
+    pub fn new(torus: Torus, sources: Vec<Position>) -> DistanceField {
+        let mut routes: Grid<Option<Route>> = Grid::new(torus.width, torus.height, None);
+        let mut queue: VecDeque<Position> = VecDeque::new();
+
+        for source in &sources {
+            let source = torus.normalize(source);
+            if routes.get(&source).is_none() {
+                routes.set(&source, Some(Route { base: source, distance: 0, first_step: Direction::Still }));
+                queue.push_back(source);
+            }
+        }
+
+        while let Some(position) = queue.pop_front() {
+            let route = routes.get(&position).unwrap();
+            for direction in Direction::get_all_cardinals() {
+                let neighbour = torus.offset(&position, direction);
+                if routes.get(&neighbour).is_some() {
+                    continue;
+                }
+
+                // Stepping back the way we came leads to the base.
+                let next = Route { base: route.base, distance: route.distance + 1, first_step: direction.invert_direction() };
+                routes.set(&neighbour, Some(next));
+                queue.push_back(neighbour);
+            }
+        }
+
+        DistanceField { sources, routes }
+    }
+
+    pub fn sources(&self) -> &[Position] {
+        &self.sources
+    }
+
+    // None only when there are no bases at all.
+    pub fn route(&self, position: &Position) -> Option<Route> {
+        *self.routes.get(position)
+    }
+
+    pub fn distance(&self, position: &Position) -> Option<usize> {
+        self.route(position).map(|route| route.distance)
+    }
+
+    pub fn nearest(&self, position: &Position) -> Option<Position> {
+        self.route(position).map(|route| route.base)
+    }
+
+    pub fn first_step(&self, position: &Position) -> Option<Direction> {
+        self.route(position).map(|route| route.first_step)
+    }
+}
+
+// Distance fields to one player's bases and to everyone else's, rebuilt
+// whenever a base appears or disappears.
+pub struct BaseDistances {
+    pub player: PlayerId,
+    pub friendly: DistanceField,
+    pub enemy: DistanceField,
+}
+
+impl BaseDistances {
+    pub fn new(game: &Game, player: PlayerId) -> BaseDistances {
+        let torus = game.map.torus();
+        let (friendly, enemy) = base_positions(game, player);
+        BaseDistances {
+            player,
+            friendly: DistanceField::new(torus, friendly),
+            enemy: DistanceField::new(torus, enemy),
+        }
+    }
+
+    pub fn update_frame(&mut self, game: &Game) {
+        let torus = game.map.torus();
+        let (friendly, enemy) = base_positions(game, self.player);
+
+        if friendly != self.friendly.sources {
+            self.friendly = DistanceField::new(torus, friendly);
+        }
+        if enemy != self.enemy.sources {
+            self.enemy = DistanceField::new(torus, enemy);
+        }
+    }
+}
+
+// Shipyards first, then dropoffs by id, so the order is stable across turns.
+fn base_positions(game: &Game, player: PlayerId) -> (Vec<Position>, Vec<Position>) {
+    let mut friendly: Vec<Position> = Vec::new();
+    let mut enemy: Vec<Position> = Vec::new();
+
+    for other in &game.players {
+        let bases = if other.id == player { &mut friendly } else { &mut enemy };
+        bases.push(other.shipyard.position);
+    }
+
+    let mut dropoffs: Vec<_> = game.dropoffs.values().collect();
+    dropoffs.sort_by_key(|dropoff| dropoff.id.0);
+    for dropoff in dropoffs {
+        let bases = if dropoff.owner == player { &mut friendly } else { &mut enemy };
+        bases.push(dropoff.position);
+    }
+
+    (friendly, enemy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::constants::Constants;
+    use hlt::dropoff::Dropoff;
+    use hlt::DropoffId;
+    use hlt::fixtures;
+    use hlt::fixtures::position;
+
+    #[test]
+    fn distances_cross_the_edges() {
+        let torus = Torus::new(10, 8);
+        let sources = vec![position(0, 0), position(6, 5)];
+        let field = DistanceField::new(torus, sources.clone());
+
+        for x in 0..10 {
+            for y in 0..8 {
+                let here = position(x, y);
+                let nearest = sources.iter().map(|source| torus.distance(source, &here)).min();
+                assert_eq!(field.distance(&here), nearest);
+            }
+        }
+        assert_eq!(field.distance(&position(9, 7)), Some(2));
+        assert_eq!(field.nearest(&position(-1, -1)), Some(position(0, 0)));
+    }
+
+    #[test]
+    fn first_steps_lead_back_to_the_base() {
+        let torus = Torus::new(10, 8);
+        let field = DistanceField::new(torus, vec![position(0, 0), position(6, 5)]);
+
+        for x in 0..10 {
+            for y in 0..8 {
+                let mut here = position(x, y);
+                let route = field.route(&here).unwrap();
+                for _ in 0..route.distance {
+                    here = torus.offset(&here, field.first_step(&here).unwrap());
+                }
+                assert_eq!(here, route.base);
+                assert_eq!(field.first_step(&here), Some(Direction::Still));
+            }
+        }
+    }
+
+    #[test]
+    fn ties_go_to_the_first_base() {
+        let field = DistanceField::new(Torus::new(10, 8), vec![position(2, 0), position(6, 0)]);
+        assert_eq!(field.nearest(&position(4, 0)), Some(position(2, 0)));
+        assert!(DistanceField::new(Torus::new(10, 8), Vec::new()).route(&position(4, 0)).is_none());
+    }
+
+    #[test]
+    fn splits_bases_by_owner_and_follows_new_dropoffs() {
+        let map = fixtures::map(16, 16, |_| 0);
+        let mut game = fixtures::game(Constants::default_for(16), map, &[position(2, 2), position(12, 12)], Vec::new());
+        let mut bases = BaseDistances::new(&game, PlayerId(0));
+        assert_eq!(bases.friendly.sources(), &[position(2, 2)]);
+        assert_eq!(bases.enemy.sources(), &[position(12, 12)]);
+        assert_eq!(bases.friendly.distance(&position(8, 8)), Some(12));
+
+        game.dropoffs.insert(DropoffId(0), Dropoff { owner: PlayerId(0), id: DropoffId(0), position: position(8, 9) });
+        game.players[0].dropoff_ids.push(DropoffId(0));
+        bases.update_frame(&game);
+        assert_eq!(bases.friendly.sources(), &[position(2, 2), position(8, 9)]);
+        assert_eq!(bases.friendly.distance(&position(8, 8)), Some(1));
+        assert_eq!(bases.enemy.sources(), &[position(12, 12)]);
+    }
+}
@@ -1,6 +1,8 @@
 use hlt::DropoffId;
 use hlt::entity::Entity;
+use hlt::error::ProtocolError;
 use hlt::input::Input;
+use hlt::log::Log;
 use hlt::PlayerId;
 use hlt::position::Position;
 
@@ -22,11 +24,18 @@ impl Entity for Dropoff {
 
 impl Dropoff {
     pub fn generate(input: &mut Input, player_id: PlayerId) -> Dropoff {
-        input.read_and_parse_line();
-        let id = DropoffId(input.next_usize());
-        let x = input.next_i32();
-        let y = input.next_i32();
+        match Dropoff::try_generate(input, player_id) {
+            Ok(dropoff) => dropoff,
+            Err(e) => Log::panic(&e.to_string()),
+        }
+    }
+
+    pub fn try_generate(input: &mut Input, player_id: PlayerId) -> Result<Dropoff, ProtocolError> {
+        input.try_read_and_parse_line()?;
+        let id = DropoffId(input.try_next_usize()?);
+        let x = input.try_next_i32()?;
+        let y = input.try_next_i32()?;
 
-        Dropoff { owner: player_id, id, position: Position { x, y } }
+        Ok(Dropoff { owner: player_id, id, position: Position { x, y } })
     }
 }
@@ -0,0 +1,66 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum ProtocolErrorKind {
+    EndOfInput,
+    Io(io::Error),
+    MissingToken,
+    InvalidToken(String),
+    UnknownPlayer(usize),
+    OutsideMap(usize, usize),
+    MalformedConstants(String),
+    InvalidConstant(String, String),
+}
+
+#[derive(Debug)]
+pub struct ProtocolError {
+    pub kind: ProtocolErrorKind,
+    // None when the text didn't come straight from the input, e.g. the
+    // constants of a snapshot.
+    pub line: Option<usize>,
+    pub token: Option<usize>,
+}
+
+impl ProtocolError {
+    pub fn new(kind: ProtocolErrorKind, line: usize, token: Option<usize>) -> ProtocolError {
+        ProtocolError { kind, line: Some(line), token }
+    }
+
+    pub fn without_line(kind: ProtocolErrorKind, token: Option<usize>) -> ProtocolError {
+        ProtocolError { kind, line: None, token }
+    }
+
+    pub fn on_line(self, line: usize) -> ProtocolError {
+        ProtocolError { line: Some(line), ..self }
+    }
+
+    pub fn is_end_of_input(&self) -> bool {
+        matches!(self.kind, ProtocolErrorKind::EndOfInput)
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, self.token) {
+            (Some(line), Some(token)) => write!(f, "line {}, token {}: ", line, token)?,
+            (Some(line), None) => write!(f, "line {}: ", line)?,
+            (None, Some(token)) => write!(f, "token {}: ", token)?,
+            (None, None) => (),
+        }
+
+        match &self.kind {
+            ProtocolErrorKind::EndOfInput => write!(f, "input ended unexpectedly"),
+            ProtocolErrorKind::Io(e) => write!(f, "couldn't read input: {}", e),
+            ProtocolErrorKind::MissingToken => write!(f, "expected another token"),
+            ProtocolErrorKind::InvalidToken(token) => write!(f, "can't parse '{}'", token),
+            ProtocolErrorKind::UnknownPlayer(id) => write!(f, "unknown player {}", id),
+            ProtocolErrorKind::OutsideMap(x, y) => write!(f, "({}, {}) is outside the map", x, y),
+            ProtocolErrorKind::MalformedConstants(message) => write!(f, "malformed constants: {}", message),
+            ProtocolErrorKind::InvalidConstant(key, value) => write!(f, "for {} got '{}' and failed to parse that", key, value),
+        }
+    }
+}
+
+impl Error for ProtocolError {}
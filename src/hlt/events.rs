@@ -0,0 +1,220 @@
+use hlt::dropoff::Dropoff;
+use hlt::DropoffId;
+use hlt::game::Game;
+use hlt::inspiration::move_cost_ratio_among;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::ship::Ship;
+use hlt::ShipId;
+use std::cmp::max;
+use std::cmp::min;
+use std::collections::HashMap;
+
+pub struct Deposit {
+    pub ship_id: ShipId,
+    pub owner: PlayerId,
+    pub position: Position,
+    pub halite: usize,
+}
+
+// What changed between the previous frame and the current one. Ships that
+// disappeared are kept here since they are no longer in `Game::ships`.
+#[derive(Default)]
+pub struct TurnEvents {
+    pub spawned: Vec<ShipId>,
+    pub destroyed: Vec<Ship>,
+    pub converted: Vec<(Ship, DropoffId)>,
+    pub new_dropoffs: Vec<DropoffId>,
+    pub deposits: Vec<Deposit>,
+    pub halite_deltas: Vec<i64>,
+}
+
+impl TurnEvents {
+    // Must run before the map is updated, because move costs are paid from
+    // the halite the origin cell had during the previous turn.
+    pub fn between(
+        previous_ships: HashMap<ShipId, Ship>,
+        previous_dropoffs: &HashMap<DropoffId, Dropoff>,
+        previous_halite: &[usize],
+        game: &Game) -> TurnEvents
+    {
+        let mut events = TurnEvents::default();
+
+        for player in &game.players {
+            events.halite_deltas.push(player.halite as i64 - previous_halite[player.id.0] as i64);
+
+            for dropoff_id in &player.dropoff_ids {
+                if !previous_dropoffs.contains_key(dropoff_id) {
+                    events.new_dropoffs.push(*dropoff_id);
+                }
+            }
+
+            for ship_id in &player.ship_ids {
+                if !previous_ships.contains_key(ship_id) {
+                    events.spawned.push(*ship_id);
+                }
+            }
+        }
+
+        let mut previous_ships: Vec<Ship> = previous_ships.into_values().collect();
+        previous_ships.sort_by_key(|ship| ship.id.0);
+        let move_cost_ratios: Vec<usize> = previous_ships.iter().map(|ship| move_cost_ratio_among(game, ship, &previous_ships)).collect();
+
+        let mut inferred: Vec<Deposit> = Vec::new();
+        for (previous, ratio) in previous_ships.into_iter().zip(move_cost_ratios) {
+            let ship = match game.ships.get(&previous.id) {
+                Some(ship) => ship,
+                None => {
+                    let conversion = events
+                        .new_dropoffs
+                        .iter()
+                        .find(|id| game.dropoffs[id].position == previous.position && game.dropoffs[id].owner == previous.owner)
+                        .cloned();
+
+                    match conversion {
+                        Some(dropoff_id) => events.converted.push((previous, dropoff_id)),
+                        None => events.destroyed.push(previous),
+                    }
+                    continue;
+                }
+            };
+
+            if ship.position == previous.position || ship.halite >= previous.halite || !is_friendly_base(game, ship) {
+                continue;
+            }
+
+            let move_cost = game.map.at_position(&previous.position).halite / ratio;
+            let halite = previous.halite.saturating_sub(move_cost);
+            if halite > 0 {
+                inferred.push(Deposit { ship_id: ship.id, owner: ship.owner, position: ship.position, halite });
+            }
+        }
+
+        // Cargo can vanish on a base without reaching the bank, so deposits are
+        // only believed as far as the bank grew once spending is added back.
+        let mut income: Vec<i64> = game
+            .players
+            .iter()
+            .map(|player| events.halite_deltas[player.id.0] + spending(game, &events, player.id))
+            .collect();
+        for deposit in inferred {
+            let available = &mut income[deposit.owner.0];
+            let halite = min(deposit.halite as i64, max(*available, 0)) as usize;
+            if halite > 0 {
+                *available -= halite as i64;
+                events.deposits.push(Deposit { halite, ..deposit });
+            }
+        }
+
+        events
+    }
+}
+
+// What a player paid this turn for new ships and dropoffs. Converting ships
+// put their cargo and the cell's halite toward the cost.
+fn spending(game: &Game, events: &TurnEvents, player: PlayerId) -> i64 {
+    let spawned = events.spawned.iter().filter(|id| game.ships[id].owner == player).count();
+    let mut spent = (spawned * game.constants.ship_cost) as i64;
+
+    for (ship, _) in events.converted.iter().filter(|(ship, _)| ship.owner == player) {
+        let credit = ship.halite + game.map.at_position(&ship.position).halite;
+        spent += game.constants.dropoff_cost.saturating_sub(credit) as i64;
+    }
+
+    spent
+}
+
+// Moving is cheaper for ships that were inspired when they moved.
+fn is_friendly_base(game: &Game, ship: &Ship) -> bool {
+    let player = &game.players[ship.owner.0];
+    player.shipyard.position == ship.position
+        || player.dropoff_ids.iter().any(|id| game.dropoffs[id].position == ship.position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::constants::Constants;
+    use hlt::fixtures;
+    use hlt::fixtures::ship;
+
+    const CELL_HALITE: usize = 100;
+
+    // Player 0's shipyard is at (2, 2) and player 1's at (12, 12); every
+    // other cell holds CELL_HALITE.
+    fn game(constants: Constants, banks: [usize; 2], ships: Vec<Ship>) -> Game {
+        let shipyards = [fixtures::position(2, 2), fixtures::position(12, 12)];
+        let map = fixtures::map(16, 16, |position| if shipyards.contains(&position) { 0 } else { CELL_HALITE });
+
+        let mut game = fixtures::game(constants, map, &shipyards, ships);
+        for (player, &bank) in game.players.iter_mut().zip(&banks) {
+            player.halite = bank;
+        }
+        game
+    }
+
+    fn previous(ships: Vec<Ship>) -> HashMap<ShipId, Ship> {
+        ships.into_iter().map(|ship| (ship.id, ship)).collect()
+    }
+
+    #[test]
+    fn deposits_what_reached_the_bank() {
+        let game = game(Constants::default_for(16), [5090, 5000], vec![ship(0, 1, 2, 2, 0)]);
+        let events = TurnEvents::between(previous(vec![ship(0, 1, 2, 3, 100)]), &HashMap::new(), &[5000, 5000], &game);
+
+        assert_eq!(events.deposits.len(), 1);
+        assert_eq!(events.deposits[0].halite, 90);
+        assert_eq!(events.halite_deltas, vec![90, 0]);
+    }
+
+    #[test]
+    fn spawn_costs_do_not_hide_deposits() {
+        let game = game(Constants::default_for(16), [4090, 5000], vec![ship(0, 1, 2, 2, 0), ship(0, 2, 2, 2, 0)]);
+        let events = TurnEvents::between(previous(vec![ship(0, 1, 2, 3, 100)]), &HashMap::new(), &[5000, 5000], &game);
+
+        assert_eq!(events.spawned, vec![ShipId(2)]);
+        assert_eq!(events.deposits[0].halite, 90);
+    }
+
+    #[test]
+    fn lost_cargo_that_never_reached_the_bank_is_not_a_deposit() {
+        let game = game(Constants::default_for(16), [5000, 5000], vec![ship(0, 1, 2, 2, 0)]);
+        let events = TurnEvents::between(previous(vec![ship(0, 1, 2, 3, 100)]), &HashMap::new(), &[5000, 5000], &game);
+
+        assert!(events.deposits.is_empty());
+    }
+
+    #[test]
+    fn deposits_are_capped_by_the_bank() {
+        let game = game(Constants::default_for(16), [5040, 5000], vec![ship(0, 1, 2, 2, 0)]);
+        let events = TurnEvents::between(previous(vec![ship(0, 1, 2, 3, 100)]), &HashMap::new(), &[5000, 5000], &game);
+
+        assert_eq!(events.deposits[0].halite, 40);
+    }
+
+    #[test]
+    fn inspired_ships_pay_the_inspired_move_cost() {
+        let mut constants = Constants::default_for(16);
+        constants.inspired_move_cost_ratio = 5;
+        let enemies = || vec![ship(1, 7, 4, 3, 0), ship(1, 8, 2, 5, 0)];
+
+        let mut current = vec![ship(0, 1, 2, 2, 0)];
+        current.extend(enemies());
+        let game = game(constants, [5080, 5000], current);
+
+        let mut before = vec![ship(0, 1, 2, 3, 100)];
+        before.extend(enemies());
+        let events = TurnEvents::between(previous(before), &HashMap::new(), &[5000, 5000], &game);
+
+        assert_eq!(events.deposits[0].halite, 80);
+    }
+
+    #[test]
+    fn missing_ships_are_destroyed() {
+        let game = game(Constants::default_for(16), [5000, 5000], Vec::new());
+        let events = TurnEvents::between(previous(vec![ship(0, 1, 5, 5, 300)]), &HashMap::new(), &[5000, 5000], &game);
+
+        assert_eq!(events.destroyed.len(), 1);
+        assert!(events.deposits.is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+use hlt::constants::Constants;
+use hlt::game::Game;
+use hlt::game_map::GameMap;
+use hlt::grid::Grid;
+use hlt::map_cell::MapCell;
+use hlt::player::Player;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::ship::Ship;
+use hlt::ShipId;
+use hlt::shipyard::Shipyard;
+use std::collections::HashMap;
+
+// Hand-built games for unit tests. Player i owns the i-th shipyard, starts
+// with BANK halite and owns the listed ships with owner i; we're player 0.
+
+pub const BANK: usize = 5000;
+
+pub fn position(x: i32, y: i32) -> Position {
+    Position { x, y }
+}
+
+pub fn ship(owner: usize, id: usize, x: i32, y: i32, halite: usize) -> Ship {
+    Ship::new(PlayerId(owner), ShipId(id), position(x, y), halite, 1000)
+}
+
+pub fn map<F: Fn(Position) -> usize>(width: usize, height: usize, halite: F) -> GameMap {
+    GameMap::new(width, height, Grid::from_fn(width, height, |position| MapCell::new(position, halite(position))))
+}
+
+pub fn game(constants: Constants, map: GameMap, shipyards: &[Position], ships: Vec<Ship>) -> Game {
+    let players = shipyards
+        .iter()
+        .enumerate()
+        .map(|(i, &position)| Player {
+            id: PlayerId(i),
+            shipyard: Shipyard { owner: PlayerId(i), position },
+            halite: BANK,
+            ship_ids: ships.iter().filter(|ship| ship.owner.0 == i).map(|ship| ship.id).collect(),
+            dropoff_ids: Vec::new(),
+        }).collect();
+    let ships = ships.into_iter().map(|ship| (ship.id, ship)).collect();
+
+    Game::from_parts(constants, 1, PlayerId(0), players, ships, HashMap::new(), map)
+}
@@ -2,6 +2,9 @@ use hlt::command::Command;
 use hlt::constants::Constants;
 use hlt::dropoff::Dropoff;
 use hlt::DropoffId;
+use hlt::error::ProtocolError;
+use hlt::error::ProtocolErrorKind;
+use hlt::events::TurnEvents;
 use hlt::game_map::GameMap;
 use hlt::input::Input;
 use hlt::log::Log;
@@ -10,7 +13,15 @@ use hlt::player::Player;
 use hlt::PlayerId;
 use hlt::ship::Ship;
 use hlt::ShipId;
+use hlt::validator;
+use hlt::validator::CommandIssue;
 use std::collections::HashMap;
+use std::io;
+use std::io::stdin;
+use std::io::stdout;
+use std::io::BufRead;
+use std::io::Write;
+use std::mem;
 
 pub struct Game {
     pub constants: Constants,
@@ -20,28 +31,52 @@ pub struct Game {
     pub ships: HashMap<ShipId, Ship>,
     pub dropoffs: HashMap<DropoffId, Dropoff>,
     pub map: GameMap,
+    pub events: TurnEvents,
     input: Input,
+    output: Box<dyn Write>,
 }
 
 impl Game {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Game {
-        let mut input = Input::new();
-        let constants = Constants::new(&input.read_and_return_line());
+        Game::with_io(stdin().lock(), stdout())
+    }
+
+    pub fn try_new() -> Result<Game, ProtocolError> {
+        Game::try_with_io(stdin().lock(), stdout())
+    }
+
+    pub fn with_io(reader: impl BufRead + 'static, writer: impl Write + 'static) -> Game {
+        match Game::try_with_io(reader, writer) {
+            Ok(game) => game,
+            Err(e) => Log::panic(&e.to_string()),
+        }
+    }
 
-        input.read_and_parse_line();
-        let num_players = input.next_usize();
-        let my_id = PlayerId(input.next_usize());
+    pub fn try_with_io(reader: impl BufRead + 'static, writer: impl Write + 'static) -> Result<Game, ProtocolError> {
+        let mut input = Input::new(Box::new(reader));
+        let line = input.try_read_and_return_line()?;
+        let constants = Constants::try_new(&line).map_err(|e| e.on_line(input.line_number()))?;
+
+        input.try_read_and_parse_line()?;
+        let num_players = input.try_next_usize()?;
+        let my_id = PlayerId(input.try_next_usize()?);
 
         Log::open(my_id.0);
 
         let mut players: Vec<Player> = Vec::new();
-        for _ in 0..num_players {
-            players.push(Player::generate(&mut input));
+        for i in 0..num_players {
+            let mut player = Player::try_generate(&mut input)?;
+            player.halite = constants.initial_halite;
+            if player.id.0 != i {
+                return Err(input.error(ProtocolErrorKind::UnknownPlayer(player.id.0), 0));
+            }
+            players.push(player);
         }
 
-        let map = GameMap::generate(&mut input);
+        let map = GameMap::try_generate(&mut input)?;
 
-        Game {
+        Ok(Game {
             constants,
             turn_number: 0,
             my_id,
@@ -49,59 +84,134 @@ impl Game {
             ships: HashMap::new(),
             dropoffs: HashMap::new(),
             map,
-            input
-        }
+            events: TurnEvents::default(),
+            input,
+            output: Box::new(writer),
+        })
+    }
+
+    // A game that wasn't read from an engine, e.g. a loaded snapshot. It has
+    // no more frames to read and its commands go nowhere.
+    pub fn from_parts(
+        constants: Constants,
+        turn_number: usize,
+        my_id: PlayerId,
+        players: Vec<Player>,
+        ships: HashMap<ShipId, Ship>,
+        dropoffs: HashMap<DropoffId, Dropoff>,
+        map: GameMap) -> Game
+    {
+        let mut game = Game {
+            constants,
+            turn_number,
+            my_id,
+            players,
+            ships,
+            dropoffs,
+            map,
+            events: TurnEvents::default(),
+            input: Input::new(Box::new(io::empty())),
+            output: Box::new(io::sink()),
+        };
+
+        game.map.update_ships(game.ships.values());
+        game.mark_structures();
+        game
     }
 
-    pub fn ready(name: &str) {
-        println!("{}", name);
+    pub fn ready(&mut self, name: &str) {
+        self.write_line(name);
     }
 
-    pub fn update_frame(&mut self) {
+    // Returns false once the engine has closed the connection between frames,
+    // which is how it signals that the game is over.
+    pub fn update_frame(&mut self) -> bool {
+        match self.try_update_frame() {
+            Ok(running) => running,
+            Err(e) => Log::panic(&e.to_string()),
+        }
+    }
+
+    pub fn try_update_frame(&mut self) -> Result<bool, ProtocolError> {
         let input = &mut self.input;
 
-        input.read_and_parse_line();
-        self.turn_number = input.next_usize();
+        if let Err(e) = input.try_read_and_parse_line() {
+            if e.is_end_of_input() {
+                return Ok(false);
+            }
+            return Err(e);
+        }
+        self.turn_number = input.try_next_usize()?;
 
         Log::log(&format!("=============== TURN {} ================", self.turn_number));
 
-        self.ships.clear();
-        self.dropoffs.clear();
+        let previous_ships = mem::take(&mut self.ships);
+        let previous_dropoffs = mem::take(&mut self.dropoffs);
+        let previous_halite: Vec<usize> = self.players.iter().map(|player| player.halite).collect();
 
         for _ in 0..self.players.len() {
-            input.read_and_parse_line();
-            let current_player_id = input.next_usize();
-            let num_ships = input.next_usize();
-            let num_dropoffs = input.next_usize();
-            let halite = input.next_usize();
+            input.try_read_and_parse_line()?;
+            let current_player_id = input.try_next_usize()?;
+            let num_ships = input.try_next_usize()?;
+            let num_dropoffs = input.try_next_usize()?;
+            let halite = input.try_next_usize()?;
+
+            if current_player_id >= self.players.len() {
+                return Err(input.error(ProtocolErrorKind::UnknownPlayer(current_player_id), 0));
+            }
 
-            self.players[current_player_id].update(
+            self.players[current_player_id].try_update(
                 input,
                 self.constants.max_halite,
                 &mut self.ships,
                 &mut self.dropoffs,
                 num_ships,
                 num_dropoffs,
-                halite);
+                halite)?;
         }
 
-        self.map.update(input);
+        self.events = TurnEvents::between(previous_ships, &previous_dropoffs, &previous_halite, self);
+        self.map.try_update(&mut self.input)?;
+        self.map.attribute_mining(self.ships.values());
+        self.map.update_ships(self.ships.values());
+        self.mark_structures();
+
+        Ok(true)
+    }
 
+    fn mark_structures(&mut self) {
         for player in &self.players {
             let shipyard = &player.shipyard;
-            self.map.at_entity_mut(shipyard).structure = Structure::Shipyard(player.id);
+            self.map.at_entity_mut(shipyard).set_structure(Structure::Shipyard(player.id), player.id);
 
             for dropoff_id in &player.dropoff_ids {
                 let dropoff = &self.dropoffs[dropoff_id];
-                self.map.at_entity_mut(dropoff).structure = Structure::Dropoff(*dropoff_id);
+                self.map.at_entity_mut(dropoff).set_structure(Structure::Dropoff(*dropoff_id), player.id);
             }
         }
     }
 
-    pub fn end_turn(commands: impl Iterator<Item = Command>) {
+    pub fn validate_commands(&self, commands: &[Command]) -> Vec<CommandIssue> {
+        validator::validate(self, commands)
+    }
+
+    // Commands are sent as given; problems the engine would punish are only logged.
+    pub fn end_turn(&mut self, commands: impl Iterator<Item = Command>) {
+        let commands: Vec<Command> = commands.collect();
+        for issue in self.validate_commands(&commands) {
+            Log::log(&format!("Warning: turn {}: {}", self.turn_number, issue));
+        }
+
+        let mut line = String::new();
         for command in commands {
-            print!("{} ", command.0);
+            line.push_str(&command.to_string());
+            line.push(' ');
         }
-        println!();
+        self.write_line(&line);
+    }
+
+    fn write_line(&mut self, line: &str) {
+        writeln!(self.output, "{}", line).expect("failed writing to the engine");
+        self.output.flush().expect("failed writing to the engine");
     }
 }
@@ -1,91 +1,204 @@
 use hlt::entity::Entity;
+use hlt::error::ProtocolError;
+use hlt::error::ProtocolErrorKind;
+use hlt::grid::Grid;
 use hlt::input::Input;
+use hlt::log::Log;
 use hlt::map_cell::MapCell;
-use hlt::map_cell::Structure;
 use hlt::position::Position;
-use std::cmp::min;
+use hlt::rings::RingTable;
+use hlt::ship::Ship;
+use hlt::ShipId;
+use hlt::torus::Torus;
+use std::collections::HashMap;
+
+pub struct CellChange {
+    pub position: Position,
+    pub old_halite: usize,
+    pub new_halite: usize,
+    pub miner: Option<ShipId>,
+}
+
+impl CellChange {
+    pub fn delta(&self) -> i64 {
+        self.new_halite as i64 - self.old_halite as i64
+    }
+
+    pub fn is_mined(&self) -> bool {
+        self.new_halite < self.old_halite
+    }
+
+    // Halite only ever rises on a cell when sunk ships spill their cargo.
+    pub fn is_spill(&self) -> bool {
+        self.new_halite > self.old_halite
+    }
+}
 
 pub struct GameMap {
     pub width: usize,
     pub height: usize,
-    pub cells: Vec<Vec<MapCell>>,
+    pub cells: Grid<MapCell>,
+    pub changed_cells: Vec<CellChange>,
+    rings: RingTable,
 }
 
 impl GameMap {
+    pub fn new(width: usize, height: usize, cells: Grid<MapCell>) -> GameMap {
+        let rings = RingTable::new(Torus::new(width, height));
+        GameMap { width, height, cells, changed_cells: Vec::new(), rings }
+    }
+
     pub fn at_position(&self, position: &Position) -> &MapCell {
-        let normalized = self.normalize(position);
-        &self.cells[normalized.y as usize][normalized.x as usize]
+        self.cells.get(position)
     }
 
     pub fn at_position_mut(&mut self, position: &Position) -> &mut MapCell {
-        let normalized = self.normalize(position);
-        &mut self.cells[normalized.y as usize][normalized.x as usize]
+        self.cells.get_mut(position)
     }
 
-    pub fn at_entity(&self, entity: &Entity) -> &MapCell {
+    pub fn at_entity(&self, entity: &dyn Entity) -> &MapCell {
         self.at_position(&entity.position())
     }
 
-    pub fn at_entity_mut(&mut self, entity: &Entity) -> &mut MapCell {
+    pub fn at_entity_mut(&mut self, entity: &dyn Entity) -> &mut MapCell {
         self.at_position_mut(&entity.position())
     }
 
+    pub fn torus(&self) -> Torus {
+        Torus::new(self.width, self.height)
+    }
+
     pub fn calculate_distance(&self, source: &Position, target: &Position) -> usize {
-        let normalized_source = self.normalize(source);
-        let normalized_target = self.normalize(target);
+        self.torus().distance(source, target)
+    }
 
-        let dx = (normalized_source.x - normalized_target.x).abs() as usize;
-        let dy = (normalized_source.y - normalized_target.y).abs() as usize;
+    pub fn normalize(&self, position: &Position) -> Position {
+        self.torus().normalize(position)
+    }
 
-        let toroidal_dx = min(dx, self.width - dx);
-        let toroidal_dy = min(dy, self.height - dy);
+    pub fn cells_within(&self, center: &Position, radius: usize) -> impl Iterator<Item = &MapCell> {
+        self.cells_at_offsets(center, self.rings.within(radius))
+    }
 
-        return toroidal_dx + toroidal_dy;
+    pub fn cells_at(&self, center: &Position, radius: usize) -> impl Iterator<Item = &MapCell> {
+        self.cells_at_offsets(center, self.rings.ring(radius))
     }
 
-    pub fn normalize(&self, position: &Position) -> Position {
-        let width = self.width as i32;
-        let height = self.height as i32;
-        let x = ((position.x % width) + width) % width;
-        let y = ((position.y % height) + height) % height;
-        Position { x, y }
+    // The ship and structure queries yield the cells holding them, which carry
+    // both the id and the owner.
+    pub fn ships_within(&self, center: &Position, radius: usize) -> impl Iterator<Item = &MapCell> {
+        self.cells_within(center, radius).filter(|cell| cell.is_occupied())
+    }
+
+    pub fn ships_at(&self, center: &Position, radius: usize) -> impl Iterator<Item = &MapCell> {
+        self.cells_at(center, radius).filter(|cell| cell.is_occupied())
+    }
+
+    pub fn structures_within(&self, center: &Position, radius: usize) -> impl Iterator<Item = &MapCell> {
+        self.cells_within(center, radius).filter(|cell| cell.structure.is_some())
+    }
+
+    pub fn structures_at(&self, center: &Position, radius: usize) -> impl Iterator<Item = &MapCell> {
+        self.cells_at(center, radius).filter(|cell| cell.structure.is_some())
+    }
+
+    fn cells_at_offsets<'a>(&'a self, center: &Position, offsets: &'a [(i32, i32)]) -> impl Iterator<Item = &'a MapCell> {
+        let center = *center;
+        offsets
+            .iter()
+            .map(move |&(dx, dy)| self.cells.get(&Position { x: center.x + dx, y: center.y + dy }))
     }
 
     pub fn update(&mut self, input: &mut Input) {
-        input.read_and_parse_line();
-        let update_count = input.next_usize();
+        if let Err(e) = self.try_update(input) {
+            Log::panic(&e.to_string());
+        }
+    }
+
+    pub fn try_update(&mut self, input: &mut Input) -> Result<(), ProtocolError> {
+        self.changed_cells.clear();
+
+        input.try_read_and_parse_line()?;
+        let update_count = input.try_next_usize()?;
 
         for _ in 0..update_count {
-            input.read_and_parse_line();
-            let x = input.next_usize();
-            let y = input.next_usize();
-            let halite = input.next_usize();
+            input.try_read_and_parse_line()?;
+            let x = input.try_next_usize()?;
+            let y = input.try_next_usize()?;
+            let halite = input.try_next_usize()?;
 
-            self.cells[y][x].halite = halite;
+            if x >= self.width || y >= self.height {
+                return Err(input.error(ProtocolErrorKind::OutsideMap(x, y), 0));
+            }
+
+            let cell = &mut self.cells[Position { x: x as i32, y: y as i32 }];
+            if cell.halite != halite {
+                self.changed_cells.push(CellChange {
+                    position: cell.position,
+                    old_halite: cell.halite,
+                    new_halite: halite,
+                    miner: None,
+                });
+            }
+            cell.halite = halite;
         }
+
+        Ok(())
     }
 
-    pub fn generate(input: &mut Input) -> GameMap {
-        input.read_and_parse_line();
-        let width = input.next_usize();
-        let height = input.next_usize();
+    // Ships that stay on a cell are the only ones that take halite from it.
+    pub fn attribute_mining<'a>(&mut self, ships: impl Iterator<Item = &'a Ship>) {
+        let occupants: HashMap<Position, ShipId> = ships.map(|ship| (self.normalize(&ship.position), ship.id)).collect();
 
-        let mut cells: Vec<Vec<MapCell>> = Vec::with_capacity(height);
-        for y in 0..height {
-            input.read_and_parse_line();
+        for change in &mut self.changed_cells {
+            if change.is_mined() {
+                change.miner = occupants.get(&change.position).cloned();
+            }
+        }
+    }
 
-            let mut row: Vec<MapCell> = Vec::with_capacity(width);
-            for x in 0..width {
-                let halite = input.next_usize();
+    pub fn update_ships<'a>(&mut self, ships: impl Iterator<Item = &'a Ship>) {
+        for cell in self.cells.iter_mut() {
+            cell.clear_ship();
+        }
 
-                let position = Position { x: x as i32, y: y as i32 };
-                let cell = MapCell { position, halite, structure: Structure::None };
-                row.push(cell);
-            }
+        for ship in ships {
+            self.at_entity_mut(ship).mark_ship(ship.id, ship.owner);
+        }
+    }
+
+    pub fn mined_cells(&self) -> impl Iterator<Item = &CellChange> {
+        self.changed_cells.iter().filter(|change| change.is_mined())
+    }
 
-            cells.push(row);
+    pub fn spilled_cells(&self) -> impl Iterator<Item = &CellChange> {
+        self.changed_cells.iter().filter(|change| change.is_spill())
+    }
+
+    pub fn generate(input: &mut Input) -> GameMap {
+        match GameMap::try_generate(input) {
+            Ok(map) => map,
+            Err(e) => Log::panic(&e.to_string()),
         }
+    }
+
+    pub fn try_generate(input: &mut Input) -> Result<GameMap, ProtocolError> {
+        input.try_read_and_parse_line()?;
+        let width = input.try_next_usize()?;
+        let height = input.try_next_usize()?;
+
+        let mut halite: Vec<usize> = Vec::with_capacity(width * height);
+        for _ in 0..height {
+            input.try_read_and_parse_line()?;
+            for _ in 0..width {
+                halite.push(input.try_next_usize()?);
+            }
+        }
+
+        let cells = Grid::from_fn(width, height, |position| {
+            MapCell::new(position, halite[position.y as usize * width + position.x as usize])
+        });
 
-        GameMap { width, height, cells }
+        Ok(GameMap::new(width, height, cells))
     }
 }
@@ -0,0 +1,195 @@
+use hlt::direction::Direction;
+use hlt::position::Position;
+use hlt::torus::Torus;
+use std::ops::Index;
+use std::ops::IndexMut;
+use std::slice;
+
+// Row-major storage for one value per map cell. Positions wrap around the
+// edges, so any `Position` is a valid index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    data: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Grid<T> where T: Clone {
+        Grid { width, height, data: vec![value; width * height] }
+    }
+
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(Position) -> T) -> Grid<T> {
+        let mut data: Vec<T> = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                data.push(f(Position { x: x as i32, y: y as i32 }));
+            }
+        }
+        Grid { width, height, data }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn torus(&self) -> Torus {
+        Torus::new(self.width, self.height)
+    }
+
+    pub fn normalize(&self, position: &Position) -> Position {
+        self.torus().normalize(position)
+    }
+
+    pub fn index_of(&self, position: &Position) -> usize {
+        let normalized = self.normalize(position);
+        normalized.y as usize * self.width + normalized.x as usize
+    }
+
+    pub fn position_of(&self, index: usize) -> Position {
+        Position { x: (index % self.width) as i32, y: (index / self.width) as i32 }
+    }
+
+    pub fn get(&self, position: &Position) -> &T {
+        &self.data[self.index_of(position)]
+    }
+
+    pub fn get_mut(&mut self, position: &Position) -> &mut T {
+        let index = self.index_of(position);
+        &mut self.data[index]
+    }
+
+    pub fn set(&mut self, position: &Position, value: T) {
+        *self.get_mut(position) = value;
+    }
+
+    pub fn fill(&mut self, value: T) where T: Clone {
+        for cell in &mut self.data {
+            *cell = value.clone();
+        }
+    }
+
+    pub fn neighbours(&self, position: &Position) -> impl Iterator<Item = Position> {
+        let position = *position;
+        let torus = self.torus();
+        Direction::get_all_cardinals()
+            .into_iter()
+            .map(move |direction| torus.offset(&position, direction))
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.data.iter_mut()
+    }
+
+    pub fn rows(&self) -> slice::Chunks<'_, T> {
+        self.data.chunks(self.width)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.data.len()).map(move |index| self.position_of(index))
+    }
+
+    pub fn enumerate(&self) -> impl Iterator<Item = (Position, &T)> + '_ {
+        self.data.iter().enumerate().map(move |(index, value)| (self.position_of(index), value))
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid { width: self.width, height: self.height, data: self.data.iter().map(f).collect() }
+    }
+
+    pub fn zip_with<U, V>(&self, other: &Grid<U>, mut f: impl FnMut(&T, &U) -> V) -> Grid<V> {
+        assert!(self.width == other.width && self.height == other.height, "grid sizes differ");
+        let data = self.data.iter().zip(other.data.iter()).map(|(a, b)| f(a, b)).collect();
+        Grid { width: self.width, height: self.height, data }
+    }
+}
+
+impl<T> Index<Position> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, position: Position) -> &T {
+        self.get(&position)
+    }
+}
+
+impl<T> IndexMut<Position> for Grid<T> {
+    fn index_mut(&mut self, position: Position) -> &mut T {
+        self.get_mut(&position)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Grid<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.data.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::fixtures::position;
+
+    fn grid() -> Grid<i32> {
+        Grid::from_fn(4, 3, |position| position.y * 10 + position.x)
+    }
+
+    #[test]
+    fn lays_cells_out_row_by_row() {
+        let grid = grid();
+        assert_eq!(grid.len(), 12);
+        assert_eq!(grid.index_of(&position(3, 1)), 7);
+        assert_eq!(grid.position_of(7), position(3, 1));
+        assert_eq!(grid.rows().map(|row| row.to_vec()).nth(2), Some(vec![20, 21, 22, 23]));
+        assert!(grid.enumerate().all(|(position, &value)| value == position.y * 10 + position.x));
+    }
+
+    #[test]
+    fn wraps_negative_and_overflowing_positions() {
+        let mut grid = grid();
+        assert_eq!(*grid.get(&position(-1, 0)), 3);
+        assert_eq!(*grid.get(&position(0, -1)), 20);
+        assert_eq!(*grid.get(&position(4, 3)), 0);
+        assert_eq!(grid[position(-5, 7)], 13);
+        assert_eq!(grid.index_of(&position(-4 * 100 + 2, 3 * 100 + 1)), 6);
+
+        grid.set(&position(5, -2), 99);
+        assert_eq!(grid[position(1, 1)], 99);
+        grid[position(-1, -1)] = -1;
+        assert_eq!(*grid.get(&position(3, 2)), -1);
+    }
+
+    #[test]
+    fn neighbours_wrap_around() {
+        let grid = grid();
+        let neighbours: Vec<Position> = grid.neighbours(&position(0, 0)).collect();
+        assert_eq!(neighbours, vec![position(0, 2), position(0, 1), position(1, 0), position(3, 0)]);
+    }
+
+    #[test]
+    fn maps_and_zips_cell_by_cell() {
+        let grid = grid();
+        let doubled = grid.map(|value| value * 2);
+        let sums = grid.zip_with(&doubled, |a, b| a + b);
+        assert_eq!(sums[position(2, 1)], 36);
+        assert_eq!((sums.width(), sums.height()), (4, 3));
+    }
+}
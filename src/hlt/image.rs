@@ -0,0 +1,440 @@
+use hlt::direction::Direction;
+use hlt::game::Game;
+use hlt::map_cell::Structure;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::torus::Torus;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b }
+    }
+
+    pub fn mix(&self, other: Color, t: f64) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Color::new(channel(self.r, other.r), channel(self.g, other.g), channel(self.b, other.b))
+    }
+
+    fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+pub const WHITE: Color = Color::new(255, 255, 255);
+const EMPTY_SEA: Color = Color::new(8, 12, 40);
+const RICH_SEA: Color = Color::new(250, 200, 40);
+const PLAYER_COLORS: [Color; 4] = [
+    Color::new(230, 60, 60),
+    Color::new(60, 140, 240),
+    Color::new(60, 200, 90),
+    Color::new(190, 80, 220),
+];
+
+pub fn player_color(player: PlayerId) -> Color {
+    PLAYER_COLORS[player.0 % PLAYER_COLORS.len()]
+}
+
+// Extra marks drawn on top of the map, e.g. a turn's planned moves or the
+// cells ships are heading for.
+pub struct Overlay {
+    pub color: Color,
+    pub targets: Vec<Position>,
+    pub moves: Vec<(Position, Direction)>,
+}
+
+impl Overlay {
+    pub fn new(color: Color) -> Overlay {
+        Overlay { color, targets: Vec::new(), moves: Vec::new() }
+    }
+}
+
+enum Shape {
+    Tile(Position, Color),
+    Shipyard(Position, Color),
+    Dropoff(Position, Color),
+    Ship(Position, Color, f64),
+    Target(Position, Color),
+    Arrow(Position, Direction, Color),
+}
+
+// A picture of one turn, `scale` pixels per cell, that can be written as
+// PPM, PNG or SVG.
+pub struct MapImage {
+    torus: Torus,
+    width: usize,
+    height: usize,
+    scale: usize,
+    shapes: Vec<Shape>,
+}
+
+impl MapImage {
+    pub fn from_game(game: &Game, scale: usize) -> MapImage {
+        let max_halite = game.constants.max_halite.max(1) as f64;
+        let mut image = MapImage {
+            torus: game.map.torus(),
+            width: game.map.width,
+            height: game.map.height,
+            scale: scale.max(1),
+            shapes: Vec::new(),
+        };
+
+        for cell in &game.map.cells {
+            let richness = cell.halite as f64 / max_halite;
+            image.shapes.push(Shape::Tile(cell.position, EMPTY_SEA.mix(RICH_SEA, richness)));
+        }
+
+        for cell in &game.map.cells {
+            let color = player_color(cell.structure_owner.unwrap_or(PlayerId(0)));
+            match cell.structure {
+                Structure::Shipyard(_) => image.shapes.push(Shape::Shipyard(cell.position, color)),
+                Structure::Dropoff(_) => image.shapes.push(Shape::Dropoff(cell.position, color)),
+                Structure::None => {}
+            }
+        }
+
+        let mut ships: Vec<_> = game.ships.values().collect();
+        ships.sort_by_key(|ship| ship.id.0);
+        for ship in ships {
+            let cargo = ship.halite as f64 / max_halite;
+            image.shapes.push(Shape::Ship(ship.position, player_color(ship.owner), cargo));
+        }
+
+        image
+    }
+
+    // Overlay positions may come off the map, e.g. a target one step past an
+    // edge; they're drawn where they wrap to.
+    pub fn add_overlay(&mut self, overlay: &Overlay) {
+        for target in &overlay.targets {
+            self.shapes.push(Shape::Target(self.torus.normalize(target), overlay.color));
+        }
+        for &(position, direction) in &overlay.moves {
+            if direction != Direction::Still {
+                self.shapes.push(Shape::Arrow(self.torus.normalize(&position), direction, overlay.color));
+            }
+        }
+    }
+
+    // Picks the format from the file extension.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let bytes = match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("ppm") => self.to_ppm(),
+            Some("png") => self.to_png(),
+            Some("svg") => self.to_svg().into_bytes(),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown image format for {}", path))),
+        };
+
+        File::create(path)?.write_all(&bytes)
+    }
+
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let raster = self.rasterize();
+        let mut bytes = format!("P6\n{} {}\n255\n", raster.width, raster.height).into_bytes();
+        for pixel in &raster.pixels {
+            bytes.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+        }
+        bytes
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let raster = self.rasterize();
+
+        let mut scanlines: Vec<u8> = Vec::with_capacity((raster.width * 3 + 1) * raster.height);
+        for row in raster.pixels.chunks(raster.width) {
+            scanlines.push(0);
+            for pixel in row {
+                scanlines.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+            }
+        }
+
+        let mut header: Vec<u8> = Vec::with_capacity(13);
+        header.extend_from_slice(&(raster.width as u32).to_be_bytes());
+        header.extend_from_slice(&(raster.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut bytes: Vec<u8> = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        write_png_chunk(&mut bytes, b"IHDR", &header);
+        write_png_chunk(&mut bytes, b"IDAT", &zlib_stored(&scanlines));
+        write_png_chunk(&mut bytes, b"IEND", &[]);
+        bytes
+    }
+
+    pub fn to_svg(&self) -> String {
+        let s = self.scale as f64;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" shape-rendering=\"crispEdges\">\n",
+            self.width * self.scale,
+            self.height * self.scale
+        );
+
+        for shape in &self.shapes {
+            let element = match *shape {
+                Shape::Tile(position, color) => {
+                    let (x, y) = corner(&position, s);
+                    format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>", x, y, s, s, color.hex())
+                }
+                Shape::Shipyard(position, color) => {
+                    let (x, y) = corner(&position, s);
+                    let inset = s / 8.0;
+                    format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                        x + inset, y + inset, s - 2.0 * inset, s - 2.0 * inset, color.hex()
+                    )
+                }
+                Shape::Dropoff(position, color) => {
+                    let (x, y) = corner(&position, s);
+                    let inset = s / 8.0;
+                    format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+                        x + 1.5 * inset, y + 1.5 * inset, s - 3.0 * inset, s - 3.0 * inset, color.hex(), inset
+                    )
+                }
+                Shape::Ship(position, color, cargo) => {
+                    let (x, y) = center(&position, s);
+                    let radius = s * 0.35;
+                    format!(
+                        "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/><circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+                        x, y, radius, color.hex(), x, y, radius * cargo.clamp(0.0, 1.0).sqrt() * 0.8, WHITE.hex()
+                    )
+                }
+                Shape::Target(position, color) => {
+                    let (x, y) = corner(&position, s);
+                    let width = (s / 10.0).max(1.0);
+                    format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+                        x + width / 2.0, y + width / 2.0, s - width, s - width, color.hex(), width
+                    )
+                }
+                Shape::Arrow(position, direction, color) => {
+                    let (x, y) = center(&position, s);
+                    let (dx, dy) = unit(direction);
+                    format!(
+                        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>",
+                        x, y, x + dx * s / 2.0, y + dy * s / 2.0, color.hex(), (s / 8.0).max(1.0)
+                    )
+                }
+            };
+
+            svg.push_str(&element);
+            svg.push('\n');
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn rasterize(&self) -> Raster {
+        let s = self.scale;
+        let mut raster = Raster { width: self.width * s, height: self.height * s, pixels: Vec::new() };
+        raster.pixels = vec![EMPTY_SEA; raster.width * raster.height];
+
+        for shape in &self.shapes {
+            match *shape {
+                Shape::Tile(position, color) => {
+                    let (x, y) = pixel_corner(&position, s);
+                    raster.fill_rect(x, y, x + s, y + s, color);
+                }
+                Shape::Shipyard(position, color) => {
+                    let (x, y) = pixel_corner(&position, s);
+                    let inset = s / 8;
+                    raster.fill_rect(x + inset, y + inset, x + s - inset, y + s - inset, color);
+                }
+                Shape::Dropoff(position, color) => {
+                    let (x, y) = pixel_corner(&position, s);
+                    let inset = s / 8;
+                    raster.outline_rect(x + inset, y + inset, x + s - inset, y + s - inset, inset.max(1), color);
+                }
+                Shape::Ship(position, color, cargo) => {
+                    let (x, y) = center(&position, s as f64);
+                    let radius = s as f64 * 0.35;
+                    raster.fill_circle(x, y, radius, color);
+                    raster.fill_circle(x, y, radius * cargo.clamp(0.0, 1.0).sqrt() * 0.8, WHITE);
+                }
+                Shape::Target(position, color) => {
+                    let (x, y) = pixel_corner(&position, s);
+                    raster.outline_rect(x, y, x + s, y + s, (s / 10).max(1), color);
+                }
+                Shape::Arrow(position, direction, color) => {
+                    let (x, y) = pixel_corner(&position, s);
+                    let half = s / 2;
+                    let thickness = (s / 8).max(1);
+                    let (x0, y0, x1, y1) = match direction {
+                        Direction::North => (x + half, y, x + half + thickness, y + half),
+                        Direction::South => (x + half, y + half, x + half + thickness, y + s),
+                        Direction::West => (x, y + half, x + half, y + half + thickness),
+                        Direction::East => (x + half, y + half, x + s, y + half + thickness),
+                        Direction::Still => continue,
+                    };
+                    raster.fill_rect(x0, y0, x1, y1, color);
+                }
+            }
+        }
+
+        raster
+    }
+}
+
+struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Raster {
+    fn fill_rect(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: Color) {
+        for y in y0..y1.min(self.height) {
+            for x in x0..x1.min(self.width) {
+                self.pixels[y * self.width + x] = color;
+            }
+        }
+    }
+
+    fn outline_rect(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, thickness: usize, color: Color) {
+        self.fill_rect(x0, y0, x1, y0 + thickness, color);
+        self.fill_rect(x0, y1.saturating_sub(thickness), x1, y1, color);
+        self.fill_rect(x0, y0, x0 + thickness, y1, color);
+        self.fill_rect(x1.saturating_sub(thickness), y0, x1, y1, color);
+    }
+
+    fn fill_circle(&mut self, cx: f64, cy: f64, radius: f64, color: Color) {
+        let x0 = (cx - radius).floor().max(0.0) as usize;
+        let y0 = (cy - radius).floor().max(0.0) as usize;
+        let x1 = ((cx + radius).ceil() as usize).min(self.width);
+        let y1 = ((cy + radius).ceil() as usize).min(self.height);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let dx = x as f64 + 0.5 - cx;
+                let dy = y as f64 + 0.5 - cy;
+                if dx * dx + dy * dy <= radius * radius {
+                    self.pixels[y * self.width + x] = color;
+                }
+            }
+        }
+    }
+}
+
+fn pixel_corner(position: &Position, scale: usize) -> (usize, usize) {
+    (position.x as usize * scale, position.y as usize * scale)
+}
+
+fn corner(position: &Position, scale: f64) -> (f64, f64) {
+    (position.x as f64 * scale, position.y as f64 * scale)
+}
+
+fn center(position: &Position, scale: f64) -> (f64, f64) {
+    ((position.x as f64 + 0.5) * scale, (position.y as f64 + 0.5) * scale)
+}
+
+fn unit(direction: Direction) -> (f64, f64) {
+    match direction {
+        Direction::North => (0.0, -1.0),
+        Direction::South => (0.0, 1.0),
+        Direction::East => (1.0, 0.0),
+        Direction::West => (-1.0, 0.0),
+        Direction::Still => (0.0, 0.0),
+    }
+}
+
+fn write_png_chunk(bytes: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Deflate's stored blocks are enough for a valid PNG; the files are larger
+// than compressed ones but need no compressor.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        bytes.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        bytes.push(if last { 1 } else { 0 });
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(&(!length).to_le_bytes());
+        bytes.extend_from_slice(block);
+    }
+
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::constants::Constants;
+    use hlt::fixtures;
+
+    const SCALE: usize = 4;
+
+    fn game() -> Game {
+        fixtures::game(Constants::default_for(8), fixtures::map(8, 6, |_| 0), &[fixtures::position(2, 2)], Vec::new())
+    }
+
+    fn pixel(ppm: &[u8], x: usize, y: usize) -> Color {
+        // "P6\n32 24\n255\n" comes before the pixels.
+        let start = 13 + (y * 8 * SCALE + x) * 3;
+        Color::new(ppm[start], ppm[start + 1], ppm[start + 2])
+    }
+
+    #[test]
+    fn off_map_overlays_wrap() {
+        let color = Color::new(1, 2, 3);
+        let mut overlay = Overlay::new(color);
+        overlay.targets.push(Position { x: -1, y: -3 });
+        overlay.moves.push((Position { x: 10, y: 7 }, Direction::East));
+
+        let mut image = MapImage::from_game(&game(), SCALE);
+        image.add_overlay(&overlay);
+        let ppm = image.to_ppm();
+
+        assert_eq!(ppm.len(), 13 + 8 * SCALE * 6 * SCALE * 3);
+        // The target lands on (7, 3) and the arrow starts from (2, 1).
+        assert_eq!(pixel(&ppm, 7 * SCALE, 3 * SCALE), color);
+        assert_eq!(pixel(&ppm, 3 * SCALE - 1, SCALE + SCALE / 2), color);
+        assert_ne!(pixel(&ppm, 0, 0), color);
+
+        let svg = image.to_svg();
+        assert!(!svg.contains("=\"-"));
+    }
+}
@@ -1,33 +1,62 @@
+use hlt::error::ProtocolError;
+use hlt::error::ProtocolErrorKind;
 use hlt::log::Log;
-use std::io::stdin;
+use std::io::BufRead;
 use std::process::exit;
 use std::str::FromStr;
 
 pub struct Input {
+    reader: Box<dyn BufRead>,
     tokens: Vec<String>,
     current_token: usize,
+    line_number: usize,
 }
 
 impl Input {
-    pub fn new() -> Input {
-        Input { tokens: Vec::new(), current_token: 0 }
+    pub fn new(reader: Box<dyn BufRead>) -> Input {
+        Input { reader, tokens: Vec::new(), current_token: 0, line_number: 0 }
+    }
+
+    pub fn line_number(&self) -> usize {
+        self.line_number
     }
 
     pub fn read_and_return_line(&mut self) -> String {
-        let mut buf = String::new();
-        match stdin().read_line(&mut buf) {
-            Ok(_) => (),
-            Err(_) => {
+        match self.try_read_and_return_line() {
+            Ok(buf) => buf,
+            Err(ref e) if e.is_end_of_input() => {
                 Log::log("Input connection from server closed. Exiting...");
                 Log::flush();
                 exit(0);
             },
+            Err(e) => Log::panic(&e.to_string()),
+        }
+    }
+
+    // Only a clean end of stream means the engine has gone away; a failed read
+    // is reported as such.
+    pub fn try_read_and_return_line(&mut self) -> Result<String, ProtocolError> {
+        let mut buf = String::new();
+        self.line_number += 1;
+        match self.reader.read_line(&mut buf) {
+            Ok(0) => Err(ProtocolError::new(ProtocolErrorKind::EndOfInput, self.line_number, None)),
+            Ok(_) => Ok(buf),
+            Err(e) => Err(ProtocolError::new(ProtocolErrorKind::Io(e), self.line_number, None)),
         }
-        buf
     }
 
     pub fn read_and_parse_line(&mut self) {
         let buf = self.read_and_return_line();
+        self.parse_line(&buf);
+    }
+
+    pub fn try_read_and_parse_line(&mut self) -> Result<(), ProtocolError> {
+        let buf = self.try_read_and_return_line()?;
+        self.parse_line(&buf);
+        Ok(())
+    }
+
+    fn parse_line(&mut self, buf: &str) {
         let token_iter = buf.split_whitespace();
         let token_iter = token_iter.filter(|x| !x.is_empty());
         let token_iter = token_iter.map(|x| x.to_string());
@@ -44,13 +73,64 @@ impl Input {
     }
 
     pub fn next<T: FromStr>(&mut self) -> T {
-        let token = &self.tokens[self.current_token];
-        self.current_token += 1;
-
-        let result = match token.parse() {
+        match self.try_next() {
             Ok(x) => x,
-            Err(_) => Log::panic(&format!("Can't parse '{}'.", token)),
+            Err(e) => Log::panic(&e.to_string()),
+        }
+    }
+
+    pub fn try_next_i32(&mut self) -> Result<i32, ProtocolError> {
+        self.try_next()
+    }
+
+    pub fn try_next_usize(&mut self) -> Result<usize, ProtocolError> {
+        self.try_next()
+    }
+
+    pub fn try_next<T: FromStr>(&mut self) -> Result<T, ProtocolError> {
+        let position = self.current_token;
+        let token = match self.tokens.get(position) {
+            Some(token) => token,
+            None => return Err(self.error(ProtocolErrorKind::MissingToken, position)),
         };
-        result
+        self.current_token += 1;
+
+        match token.parse() {
+            Ok(x) => Ok(x),
+            Err(_) => Err(self.error(ProtocolErrorKind::InvalidToken(token.clone()), position)),
+        }
+    }
+
+    pub fn error(&self, kind: ProtocolErrorKind, token: usize) -> ProtocolError {
+        ProtocolError::new(kind, self.line_number, Some(token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn input(bytes: &[u8]) -> Input {
+        Input::new(Box::new(Cursor::new(bytes.to_vec())))
+    }
+
+    #[test]
+    fn reads_lines_until_the_end_of_input() {
+        let mut input = input(b"1 2\n");
+        assert_eq!(input.try_read_and_return_line().unwrap(), "1 2\n");
+
+        let error = input.try_read_and_return_line().unwrap_err();
+        assert!(error.is_end_of_input());
+        assert_eq!(error.line, Some(2));
+    }
+
+    #[test]
+    fn read_errors_are_not_the_end_of_input() {
+        let mut input = input(b"\xff\xfe\n");
+
+        let error = input.try_read_and_return_line().unwrap_err();
+        assert!(!error.is_end_of_input());
+        assert!(matches!(error.kind, ProtocolErrorKind::Io(_)));
     }
 }
@@ -0,0 +1,142 @@
+use hlt::constants::Constants;
+use hlt::game::Game;
+use hlt::grid::Grid;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::ship::Ship;
+use std::cmp::min;
+
+// Which cells would inspire a ship of each player. Inspiration is decided by
+// where ships stand at the start of a turn, so a frame's grids tell both
+// whether a ship is inspired now and whether it would be next turn if it
+// ended this turn on a given cell (assuming enemies stay put).
+pub struct Inspiration {
+    enabled: bool,
+    radius: usize,
+    ship_count: usize,
+    extract_ratio: usize,
+    inspired_extract_ratio: usize,
+    bonus_multiplier: f64,
+    move_cost_ratio: usize,
+    inspired_move_cost_ratio: usize,
+    max_halite: usize,
+    inspired: Vec<Grid<bool>>,
+}
+
+impl Inspiration {
+    pub fn new(game: &Game) -> Inspiration {
+        let constants: &Constants = &game.constants;
+        let width = game.map.width;
+        let height = game.map.height;
+
+        Inspiration {
+            enabled: constants.inspiration_enabled,
+            radius: constants.inspiration_radius,
+            ship_count: constants.inspiration_ship_count,
+            extract_ratio: constants.extract_ratio,
+            inspired_extract_ratio: constants.inspired_extract_ratio,
+            bonus_multiplier: constants.inspired_bonus_multiplier,
+            move_cost_ratio: constants.move_cost_ratio,
+            inspired_move_cost_ratio: constants.inspired_move_cost_ratio,
+            max_halite: constants.max_halite,
+            inspired: game.players.iter().map(|_| Grid::new(width, height, false)).collect(),
+        }
+    }
+
+    pub fn update_frame(&mut self, game: &Game) {
+        for grid in &mut self.inspired {
+            grid.fill(false);
+        }
+
+        if !self.enabled {
+            return;
+        }
+
+        // Ships of each player, and of everyone, within the radius of each cell.
+        let mut nearby: Vec<Grid<usize>> = game.players.iter().map(|_| Grid::new(game.map.width, game.map.height, 0)).collect();
+        let mut total: Grid<usize> = Grid::new(game.map.width, game.map.height, 0);
+        for cell in &game.map.cells {
+            if let Some(owner) = cell.ship_owner {
+                for other in game.map.cells_within(&cell.position, self.radius) {
+                    nearby[owner.0][other.position] += 1;
+                    total[other.position] += 1;
+                }
+            }
+        }
+
+        let ship_count = self.ship_count;
+        for (player, grid) in self.inspired.iter_mut().enumerate() {
+            *grid = total.zip_with(&nearby[player], |all, own| all - own >= ship_count);
+        }
+    }
+
+    pub fn is_inspired(&self, player: PlayerId, position: &Position) -> bool {
+        *self.inspired[player.0].get(position)
+    }
+
+    pub fn is_ship_inspired(&self, ship: &Ship) -> bool {
+        self.is_inspired(ship.owner, &ship.position)
+    }
+
+    pub fn extract_ratio(&self, player: PlayerId, position: &Position) -> usize {
+        if self.is_inspired(player, position) {
+            self.inspired_extract_ratio
+        } else {
+            self.extract_ratio
+        }
+    }
+
+    pub fn move_cost_ratio(&self, player: PlayerId, position: &Position) -> usize {
+        if self.is_inspired(player, position) {
+            self.inspired_move_cost_ratio
+        } else {
+            self.move_cost_ratio
+        }
+    }
+
+    // Halite a ship carrying `cargo` gains by mining `cell_halite` for one
+    // turn while standing on `position`, bonus included.
+    pub fn extraction(&self, player: PlayerId, position: &Position, cargo: usize, cell_halite: usize) -> usize {
+        let room = self.max_halite.saturating_sub(cargo);
+        let extracted = min(cell_halite.div_ceil(self.extract_ratio(player, position)), room);
+        if !self.is_inspired(player, position) {
+            return extracted;
+        }
+
+        let bonus = (extracted as f64 * self.bonus_multiplier) as usize;
+        min(extracted + bonus, room)
+    }
+
+    pub fn ship_extraction(&self, ship: &Ship, cell_halite: usize) -> usize {
+        self.extraction(ship.owner, &ship.position, ship.halite, cell_halite)
+    }
+
+    pub fn move_cost(&self, ship: &Ship, cell_halite: usize) -> usize {
+        cell_halite / self.move_cost_ratio(ship.owner, &ship.position)
+    }
+}
+
+// The move cost ratio for `ship` among `ships` as they stand, for when only
+// a few ships matter or the frame is a past one.
+pub fn move_cost_ratio_among<'a, I>(game: &Game, ship: &Ship, ships: I) -> usize
+where
+    I: IntoIterator<Item = &'a Ship>,
+{
+    let constants = &game.constants;
+    if !constants.inspiration_enabled {
+        return constants.move_cost_ratio;
+    }
+
+    let torus = game.map.torus();
+    let enemies = ships
+        .into_iter()
+        .filter(|other| other.owner != ship.owner)
+        .filter(|other| torus.distance(&other.position, &ship.position) <= constants.inspiration_radius)
+        .count();
+
+    if enemies >= constants.inspiration_ship_count {
+        constants.inspired_move_cost_ratio
+    } else {
+        constants.move_cost_ratio
+    }
+}
@@ -1,11 +1,15 @@
 use hlt::DropoffId;
 use hlt::PlayerId;
 use hlt::position::Position;
+use hlt::ShipId;
 
 pub struct MapCell {
     pub position: Position,
     pub halite: usize,
     pub structure: Structure,
+    pub structure_owner: Option<PlayerId>,
+    pub ship: Option<ShipId>,
+    pub ship_owner: Option<PlayerId>,
 }
 
 #[derive(Eq, PartialEq)]
@@ -17,13 +21,59 @@ pub enum Structure {
 
 impl Structure {
     pub fn is_some(&self) -> bool {
-        match *self {
-            Structure::None => false,
-            _ => true,
-        }
+        !matches!(*self, Structure::None)
     }
 
     pub fn is_none(&self) -> bool {
         !self.is_some()
     }
 }
+
+impl MapCell {
+    pub fn new(position: Position, halite: usize) -> MapCell {
+        MapCell { position, halite, structure: Structure::None, structure_owner: None, ship: None, ship_owner: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ship.is_none() && self.structure.is_none()
+    }
+
+    pub fn is_occupied(&self) -> bool {
+        self.ship.is_some()
+    }
+
+    pub fn is_occupied_by(&self, owner: PlayerId) -> bool {
+        self.ship_owner == Some(owner)
+    }
+
+    pub fn has_friendly_ship(&self, me: PlayerId) -> bool {
+        self.is_occupied_by(me)
+    }
+
+    pub fn has_enemy_ship(&self, me: PlayerId) -> bool {
+        self.ship_owner.map(|owner| owner != me).unwrap_or(false)
+    }
+
+    pub fn has_friendly_structure(&self, me: PlayerId) -> bool {
+        self.structure_owner == Some(me)
+    }
+
+    pub fn has_enemy_structure(&self, me: PlayerId) -> bool {
+        self.structure_owner.map(|owner| owner != me).unwrap_or(false)
+    }
+
+    pub fn set_structure(&mut self, structure: Structure, owner: PlayerId) {
+        self.structure = structure;
+        self.structure_owner = Some(owner);
+    }
+
+    pub fn mark_ship(&mut self, ship_id: ShipId, owner: PlayerId) {
+        self.ship = Some(ship_id);
+        self.ship_owner = Some(owner);
+    }
+
+    pub fn clear_ship(&mut self) {
+        self.ship = None;
+        self.ship_owner = None;
+    }
+}
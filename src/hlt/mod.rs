@@ -5,14 +5,32 @@ pub mod constants;
 #[allow(dead_code)]
 pub mod direction;
 #[allow(dead_code)]
+pub mod distance_field;
+#[allow(dead_code)]
 pub mod dropoff;
 #[allow(dead_code)]
 pub mod entity;
 #[allow(dead_code)]
+pub mod error;
+#[allow(dead_code)]
+pub mod events;
+#[cfg(test)]
+pub mod fixtures;
+#[allow(dead_code)]
 pub mod game;
 #[allow(dead_code)]
 pub mod game_map;
 #[allow(dead_code)]
+pub mod grid;
+#[allow(dead_code)]
+pub mod image;
+#[allow(dead_code)]
+pub mod inspiration;
+#[allow(dead_code)]
+pub mod json;
+// Kept as shipped with the starter kit.
+#[allow(dead_code)]
+#[allow(clippy::new_without_default, clippy::expect_fun_call, clippy::single_match)]
 pub mod log;
 #[allow(dead_code)]
 pub mod map_cell;
@@ -23,9 +41,29 @@ pub mod player;
 #[allow(dead_code)]
 pub mod position;
 #[allow(dead_code)]
+pub mod reservation;
+#[allow(dead_code)]
+pub mod resolver;
+#[allow(dead_code)]
+pub mod rings;
+#[allow(dead_code)]
 pub mod ship;
 #[allow(dead_code)]
 pub mod shipyard;
+#[allow(dead_code)]
+pub mod snapshot;
+#[allow(dead_code)]
+pub mod summed_area;
+#[allow(dead_code)]
+pub mod symmetry;
+#[allow(dead_code)]
+pub mod threat;
+#[allow(dead_code)]
+pub mod torus;
+#[allow(dead_code)]
+pub mod traffic;
+#[allow(dead_code)]
+pub mod validator;
 
 #[allow(dead_code)]
 mod input;
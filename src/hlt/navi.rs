@@ -1,47 +1,78 @@
+use hlt::constants::Constants;
 use hlt::direction::Direction;
+use hlt::game_map::GameMap;
+use hlt::grid::Grid;
 use hlt::position::Position;
+use hlt::reservation::ReservationTable;
 use hlt::ship::Ship;
 use hlt::ShipId;
 use hlt::game::Game;
+use hlt::torus::Torus;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// What a step costs when routing: the halite burnt leaving a cell plus a flat
+// penalty per turn, both in halite.
+#[derive(Copy, Clone, Debug)]
+pub struct PathCost {
+    pub move_cost_ratio: usize,
+    pub turn_penalty: usize,
+}
+
+impl PathCost {
+    pub fn new(constants: &Constants, turn_penalty: usize) -> PathCost {
+        PathCost { move_cost_ratio: constants.move_cost_ratio, turn_penalty }
+    }
+}
+
+// Cost so far as (halite plus turn penalties, turns); compared in that order.
+type Cost = (usize, usize);
+
+pub struct Path {
+    // Starts with the source and ends with the destination.
+    pub positions: Vec<Position>,
+    pub directions: Vec<Direction>,
+    pub halite_cost: usize,
+}
+
+impl Path {
+    pub fn first_step(&self) -> Direction {
+        self.directions.first().cloned().unwrap_or(Direction::Still)
+    }
+
+    pub fn turns(&self) -> usize {
+        self.directions.len()
+    }
+}
 
 pub struct Navi {
     pub width: usize,
     pub height: usize,
-    pub occupied: Vec<Vec<Option<ShipId>>>,
+    pub occupied: Grid<Option<ShipId>>,
 }
 
 impl Navi {
     pub fn new(width: usize, height: usize) -> Navi {
-        let mut occupied: Vec<Vec<Option<ShipId>>> = Vec::with_capacity(height);
-        for _ in 0..height {
-            occupied.push(vec![None; width]);
-        }
-
+        let occupied = Grid::new(width, height, None);
         Navi { width, height, occupied }
     }
 
     pub fn update_frame(&mut self, game: &Game) {
         self.clear();
 
-        for player in &game.players {
-            for ship_id in &player.ship_ids {
-                let ship = &game.ships[ship_id];
-                self.mark_unsafe_ship(&ship);
+        for cell in &game.map.cells {
+            if let Some(ship_id) = cell.ship {
+                self.mark_unsafe(&cell.position, ship_id);
             }
         }
     }
 
     pub fn clear(&mut self) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                self.occupied[y][x] = None;
-            }
-        }
+        self.occupied.fill(None);
     }
 
     pub fn is_safe(&self, position: &Position) -> bool {
-        let position = self.normalize(position);
-        self.occupied[position.y as usize][position.x as usize].is_none()
+        self.occupied.get(position).is_none()
     }
 
     pub fn is_unsafe(&self, position: &Position) -> bool {
@@ -49,8 +80,7 @@ impl Navi {
     }
 
     pub fn mark_unsafe(&mut self, position: &Position, ship_id: ShipId) {
-        let position = self.normalize(position);
-        self.occupied[position.y as usize][position.x as usize] = Some(ship_id);
+        self.occupied.set(position, Some(ship_id));
     }
 
     pub fn mark_unsafe_ship(&mut self, ship: &Ship) {
@@ -58,38 +88,188 @@ impl Navi {
     }
 
     pub fn get_unsafe_moves(&self, source: &Position, destination: &Position) -> Vec<Direction> {
-        let normalized_source = self.normalize(source);
-        let normalized_destination = self.normalize(destination);
+        self.torus().directions_toward(source, destination)
+    }
+
+    pub fn naive_navigate(&mut self, ship: &Ship, destination: &Position) -> Direction {
+        let ship_position = &ship.position;
+
+        // get_unsafe_moves normalizes for us
+        for direction in self.get_unsafe_moves(ship_position, destination) {
+            let target_pos = ship_position.directional_offset(direction);
+
+            if self.is_safe(&target_pos) {
+                self.mark_unsafe(&target_pos, ship.id);
+                return direction;
+            }
+        }
+
+        Direction::Still
+    }
+
+    // A* over the torus. Of the paths with the lowest total cost the shortest
+    // one is picked, so with no turn penalty ships still don't wander.
+    pub fn find_path(&self, map: &GameMap, source: &Position, destination: &Position, cost: &PathCost) -> Path {
+        let torus = self.torus();
+        let source = torus.normalize(source);
+        let destination = torus.normalize(destination);
+
+        let heuristic = |position: &Position| {
+            let distance = torus.distance(position, &destination);
+            (distance * cost.turn_penalty, distance)
+        };
+
+        let mut best: Grid<Option<Cost>> = Grid::new(self.width, self.height, None);
+        let mut came_from: Grid<Option<Direction>> = Grid::new(self.width, self.height, None);
+        let mut queue: BinaryHeap<Reverse<(Cost, Cost, usize)>> = BinaryHeap::new();
 
-        let dx = (normalized_source.x - normalized_destination.x).abs() as usize;
-        let dy = (normalized_source.y - normalized_destination.y).abs() as usize;
+        best.set(&source, Some((0, 0)));
+        queue.push(Reverse((heuristic(&source), (0, 0), best.index_of(&source))));
 
-        let wrapped_dx = self.width - dx;
-        let wrapped_dy = self.height - dy;
+        while let Some(Reverse((_, (spent, turns), index))) = queue.pop() {
+            let position = best.position_of(index);
+            if position == destination {
+                break;
+            }
+            if best.get(&position).map(|known| known < (spent, turns)).unwrap_or(false) {
+                continue;
+            }
 
-        let mut possible_moves: Vec<Direction> = Vec::new();
+            let step = map.at_position(&position).halite / cost.move_cost_ratio + cost.turn_penalty;
+            for direction in Direction::get_all_cardinals() {
+                let next = torus.offset(&position, direction);
+                let reached = (spent + step, turns + 1);
+                if best.get(&next).map(|known| known <= reached).unwrap_or(false) {
+                    continue;
+                }
 
-        if normalized_source.x < normalized_destination.x {
-            possible_moves.push(if dx > wrapped_dx { Direction::West } else { Direction::East });
-        } else if normalized_source.x > normalized_destination.x {
-            possible_moves.push(if dx < wrapped_dx { Direction::West } else { Direction::East });
+                best.set(&next, Some(reached));
+                came_from.set(&next, Some(direction));
+                let (h_spent, h_turns) = heuristic(&next);
+                queue.push(Reverse(((reached.0 + h_spent, reached.1 + h_turns), reached, best.index_of(&next))));
+            }
         }
 
-        if normalized_source.y < normalized_destination.y {
-            possible_moves.push(if dy > wrapped_dy { Direction::North } else { Direction::South });
-        } else if normalized_source.y > normalized_destination.y {
-            possible_moves.push(if dy < wrapped_dy { Direction::North } else { Direction::South });
+        let mut positions: Vec<Position> = vec![destination];
+        let mut directions: Vec<Direction> = Vec::new();
+        let mut position = destination;
+        while position != source {
+            let direction = came_from.get(&position).unwrap();
+            position = torus.offset(&position, direction.invert_direction());
+            positions.push(position);
+            directions.push(direction);
         }
+        positions.reverse();
+        directions.reverse();
 
-        possible_moves
+        let halite_cost = positions
+            .iter()
+            .take(directions.len())
+            .map(|position| map.at_position(position).halite / cost.move_cost_ratio)
+            .sum();
+
+        Path { positions, directions, halite_cost }
     }
 
-    pub fn naive_navigate(&mut self, ship: &Ship, destination: &Position) -> Direction {
-        let ship_position = &ship.position;
+    // A* through space and time that keeps clear of cells other ships have
+    // reserved, waiting in place when that is cheaper than going around.
+    // Reservations only reach `reservations.horizon` turns ahead; past that the
+    // search carries on as in find_path. None when the ship is boxed in.
+    pub fn find_cooperative_path(
+        &self,
+        map: &GameMap,
+        reservations: &ReservationTable,
+        ship_id: ShipId,
+        source: &Position,
+        destination: &Position,
+        cost: &PathCost,
+    ) -> Option<Path> {
+        let torus = self.torus();
+        let source = torus.normalize(source);
+        let destination = torus.normalize(destination);
+        let horizon = reservations.horizon;
 
-        // get_unsafe_moves normalizes for us
-        for direction in self.get_unsafe_moves(&ship_position, destination) {
-            let target_pos = ship_position.directional_offset(direction);
+        let heuristic = |position: &Position| {
+            let distance = torus.distance(position, &destination);
+            (distance * cost.turn_penalty, distance)
+        };
+
+        // One layer per turn up to the horizon; the last layer is timeless.
+        let mut best: Vec<Grid<Option<Cost>>> = vec![Grid::new(self.width, self.height, None); horizon + 1];
+        let mut came_from: Vec<Grid<Option<(Direction, usize)>>> =
+            vec![Grid::new(self.width, self.height, None); horizon + 1];
+        let mut queue: BinaryHeap<Reverse<(Cost, Cost, usize, usize)>> = BinaryHeap::new();
+
+        best[0].set(&source, Some((0, 0)));
+        queue.push(Reverse((heuristic(&source), (0, 0), 0, best[0].index_of(&source))));
+
+        let mut goal: Option<usize> = None;
+        while let Some(Reverse((_, (spent, turns), layer, index))) = queue.pop() {
+            let position = best[layer].position_of(index);
+            if position == destination {
+                goal = Some(layer);
+                break;
+            }
+            if best[layer].get(&position).map(|known| known < (spent, turns)).unwrap_or(false) {
+                continue;
+            }
+
+            let next_layer = (layer + 1).min(horizon);
+            let halite = map.at_position(&position).halite / cost.move_cost_ratio;
+            for direction in Direction::get_all() {
+                // Waiting only helps while there are reservations to wait out.
+                if direction == Direction::Still && layer == horizon {
+                    continue;
+                }
+
+                let next = torus.offset(&position, direction);
+                if layer < horizon && !reservations.is_free_for(&next, layer + 1, ship_id) {
+                    continue;
+                }
+
+                let step = if direction == Direction::Still { 0 } else { halite };
+                let reached = (spent + step + cost.turn_penalty, turns + 1);
+                if best[next_layer].get(&next).map(|known| known <= reached).unwrap_or(false) {
+                    continue;
+                }
+
+                best[next_layer].set(&next, Some(reached));
+                came_from[next_layer].set(&next, Some((direction, layer)));
+                let (h_spent, h_turns) = heuristic(&next);
+                queue.push(Reverse((
+                    (reached.0 + h_spent, reached.1 + h_turns),
+                    reached,
+                    next_layer,
+                    best[next_layer].index_of(&next),
+                )));
+            }
+        }
+
+        let mut layer = goal?;
+        let mut positions: Vec<Position> = vec![destination];
+        let mut directions: Vec<Direction> = Vec::new();
+        let mut halite_cost = 0;
+        let mut position = destination;
+        while let Some((direction, previous_layer)) = *came_from[layer].get(&position) {
+            position = torus.offset(&position, direction.invert_direction());
+            layer = previous_layer;
+            if direction != Direction::Still {
+                halite_cost += map.at_position(&position).halite / cost.move_cost_ratio;
+            }
+            positions.push(position);
+            directions.push(direction);
+        }
+        positions.reverse();
+        directions.reverse();
+
+        Some(Path { positions, directions, halite_cost })
+    }
+
+    // Like naive_navigate, but tries the first step of the cheapest path
+    // before falling back to the other ways toward the destination.
+    pub fn navigate(&mut self, ship: &Ship, map: &GameMap, destination: &Position, cost: &PathCost) -> Direction {
+        for direction in self.get_route_moves(map, &ship.position, destination, cost) {
+            let target_pos = self.normalized_offset(&ship.position, direction);
 
             if self.is_safe(&target_pos) {
                 self.mark_unsafe(&target_pos, ship.id);
@@ -100,16 +280,39 @@ impl Navi {
         Direction::Still
     }
 
+    // The cheapest first step followed by the remaining greedy directions.
+    pub fn get_route_moves(&self, map: &GameMap, source: &Position, destination: &Position, cost: &PathCost) -> Vec<Direction> {
+        let first_step = self.find_path(map, source, destination, cost).first_step();
+        self.get_planned_moves(source, destination, first_step)
+    }
+
+    // A planned first step followed by the remaining greedy directions. A plan
+    // to wait leaves nothing to try.
+    pub fn get_planned_moves(&self, source: &Position, destination: &Position, first_step: Direction) -> Vec<Direction> {
+        let mut moves: Vec<Direction> = Vec::new();
+        if first_step == Direction::Still {
+            return moves;
+        }
+
+        moves.push(first_step);
+        for direction in self.get_unsafe_moves(source, destination) {
+            if direction != first_step {
+                moves.push(direction);
+            }
+        }
+
+        moves
+    }
+
+    pub fn torus(&self) -> Torus {
+        Torus::new(self.width, self.height)
+    }
+
     pub fn normalize(&self, position: &Position) -> Position {
-        let width = self.width as i32;
-        let height = self.height as i32;
-        let x = ((position.x % width) + width) % width;
-        let y = ((position.y % height) + height) % height;
-        Position { x, y }
+        self.torus().normalize(position)
     }
 
     pub fn normalized_offset(&self, origin: &Position, direction: Direction) -> Position {
-        let position = origin.directional_offset(direction);
-        self.normalize(&position)
+        self.torus().offset(origin, direction)
     }
 }
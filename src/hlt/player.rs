@@ -1,6 +1,8 @@
 use hlt::dropoff::Dropoff;
 use hlt::DropoffId;
+use hlt::error::ProtocolError;
 use hlt::input::Input;
+use hlt::log::Log;
 use hlt::PlayerId;
 use hlt::position::Position;
 use hlt::ship::Ship;
@@ -17,6 +19,7 @@ pub struct Player {
 }
 
 impl Player {
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
         input: &mut Input,
@@ -26,32 +29,57 @@ impl Player {
         num_ships: usize,
         num_dropoffs: usize,
         halite: usize)
+    {
+        if let Err(e) = self.try_update(input, max_halite, ships, dropoffs, num_ships, num_dropoffs, halite) {
+            Log::panic(&e.to_string());
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_update(
+        &mut self,
+        input: &mut Input,
+        max_halite: usize,
+        ships: &mut HashMap<ShipId, Ship>,
+        dropoffs: &mut HashMap<DropoffId, Dropoff>,
+        num_ships: usize,
+        num_dropoffs: usize,
+        halite: usize) -> Result<(), ProtocolError>
     {
         self.halite = halite;
 
         self.ship_ids.clear();
         for _ in 0..num_ships {
-            let ship = Ship::generate(input, self.id, max_halite);
+            let ship = Ship::try_generate(input, self.id, max_halite)?;
             self.ship_ids.push(ship.id);
             ships.insert(ship.id, ship);
         }
 
         self.dropoff_ids.clear();
         for _ in 0..num_dropoffs {
-            let dropoff = Dropoff::generate(input, self.id);
+            let dropoff = Dropoff::try_generate(input, self.id)?;
             self.dropoff_ids.push(dropoff.id);
             dropoffs.insert(dropoff.id, dropoff);
         }
+
+        Ok(())
     }
 
     pub fn generate(input: &mut Input) -> Player {
-        input.read_and_parse_line();
-        let id = PlayerId(input.next_usize());
-        let shipyard_x = input.next_i32();
-        let shipyard_y = input.next_i32();
+        match Player::try_generate(input) {
+            Ok(player) => player,
+            Err(e) => Log::panic(&e.to_string()),
+        }
+    }
+
+    pub fn try_generate(input: &mut Input) -> Result<Player, ProtocolError> {
+        input.try_read_and_parse_line()?;
+        let id = PlayerId(input.try_next_usize()?);
+        let shipyard_x = input.try_next_i32()?;
+        let shipyard_y = input.try_next_i32()?;
 
         let shipyard = Shipyard { owner: id, position: Position { x: shipyard_x, y: shipyard_y } };
 
-        Player { id, shipyard, halite: 0, ship_ids: Vec::new(), dropoff_ids: Vec::new() }
+        Ok(Player { id, shipyard, halite: 0, ship_ids: Vec::new(), dropoff_ids: Vec::new() })
     }
 }
@@ -0,0 +1,115 @@
+use hlt::position::Position;
+use hlt::ShipId;
+use hlt::torus::Torus;
+use std::collections::HashMap;
+
+// Where friendly ships have committed to be over the next few turns, keyed by
+// (position, turns from now). Ships passing each other is legal in Halite, so
+// only cells are reserved, not the moves between them. The table is rebuilt
+// every turn and only ships with a planned route fill it; a ship that picks
+// its step some other way isn't in it, so plans only avoid each other.
+pub struct ReservationTable {
+    torus: Torus,
+    pub horizon: usize,
+    reserved: HashMap<(Position, usize), ShipId>,
+    paths: HashMap<ShipId, Vec<Position>>,
+}
+
+impl ReservationTable {
+    pub fn new(torus: Torus, horizon: usize) -> ReservationTable {
+        ReservationTable { torus, horizon, reserved: HashMap::new(), paths: HashMap::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.reserved.clear();
+        self.paths.clear();
+    }
+
+    // `positions[t]` is where the ship will be t turns from now. Anything past
+    // the horizon is dropped. Replaces the ship's previous reservation.
+    pub fn reserve_path(&mut self, ship_id: ShipId, positions: &[Position]) {
+        self.release(ship_id);
+
+        let path: Vec<Position> = positions
+            .iter()
+            .take(self.horizon + 1)
+            .map(|position| self.torus.normalize(position))
+            .collect();
+
+        for (offset, position) in path.iter().enumerate() {
+            self.reserved.insert((*position, offset), ship_id);
+        }
+        self.paths.insert(ship_id, path);
+    }
+
+    pub fn release(&mut self, ship_id: ShipId) {
+        if let Some(path) = self.paths.remove(&ship_id) {
+            for (offset, position) in path.iter().enumerate() {
+                if self.reserved.get(&(*position, offset)) == Some(&ship_id) {
+                    self.reserved.remove(&(*position, offset));
+                }
+            }
+        }
+    }
+
+    pub fn reserved_by(&self, position: &Position, offset: usize) -> Option<ShipId> {
+        self.reserved.get(&(self.torus.normalize(position), offset)).cloned()
+    }
+
+    pub fn is_reserved(&self, position: &Position, offset: usize) -> bool {
+        self.reserved_by(position, offset).is_some()
+    }
+
+    pub fn is_free_for(&self, position: &Position, offset: usize, ship_id: ShipId) -> bool {
+        self.reserved_by(position, offset).map(|owner| owner == ship_id).unwrap_or(true)
+    }
+
+    pub fn path(&self, ship_id: ShipId) -> Option<&[Position]> {
+        self.paths.get(&ship_id).map(|path| path.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::fixtures::position;
+
+    #[test]
+    fn reserves_each_step_of_a_path() {
+        let mut table = ReservationTable::new(Torus::new(8, 8), 4);
+        table.reserve_path(ShipId(1), &[position(0, 0), position(-1, 0), position(-2, 0)]);
+
+        assert_eq!(table.reserved_by(&position(0, 0), 0), Some(ShipId(1)));
+        assert_eq!(table.reserved_by(&position(7, 0), 1), Some(ShipId(1)));
+        assert_eq!(table.reserved_by(&position(-2, 8), 2), Some(ShipId(1)));
+        assert!(!table.is_reserved(&position(7, 0), 0));
+        assert!(table.is_free_for(&position(7, 0), 1, ShipId(1)));
+        assert!(!table.is_free_for(&position(7, 0), 1, ShipId(2)));
+    }
+
+    #[test]
+    fn drops_steps_past_the_horizon() {
+        let mut table = ReservationTable::new(Torus::new(8, 8), 1);
+        table.reserve_path(ShipId(1), &[position(0, 0), position(1, 0), position(2, 0)]);
+
+        assert_eq!(table.path(ShipId(1)).map(|path| path.len()), Some(2));
+        assert!(!table.is_reserved(&position(2, 0), 2));
+    }
+
+    #[test]
+    fn replacing_or_releasing_leaves_other_ships_alone() {
+        let mut table = ReservationTable::new(Torus::new(8, 8), 4);
+        table.reserve_path(ShipId(1), &[position(0, 0), position(1, 0)]);
+        table.reserve_path(ShipId(2), &[position(3, 3), position(1, 0)]);
+        table.reserve_path(ShipId(1), &[position(0, 0), position(0, 1)]);
+
+        assert!(!table.is_reserved(&position(1, 0), 0));
+        assert_eq!(table.reserved_by(&position(0, 1), 1), Some(ShipId(1)));
+        assert_eq!(table.reserved_by(&position(1, 0), 1), Some(ShipId(2)));
+
+        table.release(ShipId(1));
+        assert!(table.path(ShipId(1)).is_none());
+        assert!(!table.is_reserved(&position(0, 0), 0));
+        assert_eq!(table.reserved_by(&position(3, 3), 0), Some(ShipId(2)));
+    }
+}
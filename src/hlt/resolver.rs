@@ -0,0 +1,238 @@
+use hlt::direction::Direction;
+use hlt::position::Position;
+use hlt::ship::Ship;
+use hlt::ShipId;
+use hlt::torus::Torus;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+struct Candidates {
+    ship_id: ShipId,
+    moves: Vec<(Direction, Position, i64)>,
+}
+
+#[derive(Copy, Clone)]
+struct Edge {
+    to: usize,
+    capacity: usize,
+    cost: i64,
+}
+
+// Picks one move per ship so that no two ships end on the same cell and the
+// summed value of the chosen moves is as high as possible. It's solved as a
+// min-cost flow from ships to the cells they can reach, so swaps and longer
+// rotations come out naturally: only where ships end up matters.
+pub struct MoveResolver {
+    torus: Torus,
+    ships: Vec<Candidates>,
+    // Cells any number of our ships may end on, like a base at the end.
+    shared: Vec<Position>,
+}
+
+impl MoveResolver {
+    pub fn new(torus: Torus) -> MoveResolver {
+        MoveResolver { torus, ships: Vec::new(), shared: Vec::new() }
+    }
+
+    // Staying still is always possible, so it's added with no value when
+    // missing; that keeps every turn solvable.
+    pub fn add_ship(&mut self, ship: &Ship, candidates: &[(Direction, i64)]) {
+        let mut moves: Vec<(Direction, Position, i64)> = Vec::new();
+        for &(direction, value) in candidates {
+            if moves.iter().all(|&(other, _, _)| other != direction) {
+                moves.push((direction, self.torus.offset(&ship.position, direction), value));
+            }
+        }
+        if moves.iter().all(|&(direction, _, _)| direction != Direction::Still) {
+            moves.push((Direction::Still, self.torus.normalize(&ship.position), 0));
+        }
+
+        self.ships.push(Candidates { ship_id: ship.id, moves });
+    }
+
+    pub fn allow_stacking(&mut self, position: &Position) {
+        let position = self.torus.normalize(position);
+        if !self.shared.contains(&position) {
+            self.shared.push(position);
+        }
+    }
+
+    pub fn resolve(&self) -> HashMap<ShipId, Direction> {
+        // Nodes: source, sink, one per ship, then one per reachable cell.
+        let source = 0;
+        let sink = 1;
+        let mut cells: HashMap<Position, usize> = HashMap::new();
+        let mut edges: Vec<Edge> = Vec::new();
+        let mut graph: Vec<Vec<usize>> = vec![Vec::new(); 2 + self.ships.len()];
+
+        let mut move_edges: Vec<Vec<usize>> = Vec::new();
+        for (i, ship) in self.ships.iter().enumerate() {
+            let node = 2 + i;
+            add_edge(&mut graph, &mut edges, source, node, 1, 0);
+
+            let mut ship_edges: Vec<usize> = Vec::new();
+            for &(_, position, value) in &ship.moves {
+                let cell = match cells.get(&position) {
+                    Some(&cell) => cell,
+                    None => {
+                        let cell = graph.len();
+                        graph.push(Vec::new());
+                        cells.insert(position, cell);
+                        let capacity = if self.shared.contains(&position) { self.ships.len() } else { 1 };
+                        add_edge(&mut graph, &mut edges, cell, sink, capacity, 0);
+                        cell
+                    }
+                };
+                ship_edges.push(edges.len());
+                add_edge(&mut graph, &mut edges, node, cell, 1, -value);
+            }
+            move_edges.push(ship_edges);
+        }
+
+        // Every ship has a cell of its own to stay on, so each augmentation
+        // routes one more ship; costs are negative but there are no negative
+        // cycles, so Bellman-Ford shortest paths do.
+        for _ in 0..self.ships.len() {
+            if !augment(&graph, &mut edges, source, sink) {
+                break;
+            }
+        }
+
+        let mut directions: HashMap<ShipId, Direction> = HashMap::new();
+        for (ship, ship_edges) in self.ships.iter().zip(&move_edges) {
+            let chosen = ship_edges.iter().position(|&edge| edges[edge].capacity == 0);
+            let direction = chosen.map(|i| ship.moves[i].0).unwrap_or(Direction::Still);
+            directions.insert(ship.ship_id, direction);
+        }
+        directions
+    }
+}
+
+// Each edge is pushed with its reverse right after it, so `edge ^ 1` is the
+// other half of the pair.
+fn add_edge(graph: &mut [Vec<usize>], edges: &mut Vec<Edge>, from: usize, to: usize, capacity: usize, cost: i64) {
+    graph[from].push(edges.len());
+    edges.push(Edge { to, capacity, cost });
+    graph[to].push(edges.len());
+    edges.push(Edge { to: from, capacity: 0, cost: -cost });
+}
+
+// Pushes one unit along the cheapest path with room left. False when there
+// is none.
+fn augment(graph: &[Vec<usize>], edges: &mut [Edge], source: usize, sink: usize) -> bool {
+    let mut distance: Vec<Option<i64>> = vec![None; graph.len()];
+    let mut via: Vec<Option<usize>> = vec![None; graph.len()];
+    let mut queued: Vec<bool> = vec![false; graph.len()];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+
+    distance[source] = Some(0);
+    queue.push_back(source);
+    while let Some(node) = queue.pop_front() {
+        queued[node] = false;
+        let here = distance[node].unwrap();
+        for &edge in &graph[node] {
+            let Edge { to, capacity, cost } = edges[edge];
+            if capacity == 0 || distance[to].map(|known| known <= here + cost).unwrap_or(false) {
+                continue;
+            }
+
+            distance[to] = Some(here + cost);
+            via[to] = Some(edge);
+            if !queued[to] {
+                queued[to] = true;
+                queue.push_back(to);
+            }
+        }
+    }
+
+    if distance[sink].is_none() {
+        return false;
+    }
+
+    let mut node = sink;
+    while let Some(edge) = via[node] {
+        edges[edge].capacity -= 1;
+        edges[edge ^ 1].capacity += 1;
+        node = edges[edge ^ 1].to;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::fixtures;
+
+    fn ship(id: usize, x: i32, y: i32) -> Ship {
+        fixtures::ship(0, id, x, y, 0)
+    }
+
+    #[test]
+    fn swaps_head_on() {
+        let mut resolver = MoveResolver::new(Torus::new(8, 8));
+        resolver.add_ship(&ship(0, 2, 2), &[(Direction::East, 10)]);
+        resolver.add_ship(&ship(1, 3, 2), &[(Direction::West, 10)]);
+
+        let moves = resolver.resolve();
+        assert_eq!(moves[&ShipId(0)], Direction::East);
+        assert_eq!(moves[&ShipId(1)], Direction::West);
+    }
+
+    #[test]
+    fn rotates_three_ships() {
+        // Single steps can't close a loop of three on an even grid, but a row
+        // of a 3-wide map is one: each ship moves onto the next one's cell.
+        let mut resolver = MoveResolver::new(Torus::new(3, 8));
+        for id in 0..3 {
+            resolver.add_ship(&ship(id, id as i32, 4), &[(Direction::East, 10)]);
+        }
+
+        let moves = resolver.resolve();
+        for id in 0..3 {
+            assert_eq!(moves[&ShipId(id)], Direction::East);
+        }
+    }
+
+    #[test]
+    fn higher_value_wins_a_contested_cell() {
+        let mut resolver = MoveResolver::new(Torus::new(8, 8));
+        resolver.add_ship(&ship(0, 2, 2), &[(Direction::East, 10)]);
+        resolver.add_ship(&ship(1, 4, 2), &[(Direction::West, 30)]);
+
+        let moves = resolver.resolve();
+        assert_eq!(moves[&ShipId(0)], Direction::Still);
+        assert_eq!(moves[&ShipId(1)], Direction::West);
+    }
+
+    #[test]
+    fn a_ship_that_can_only_stay_keeps_its_cell() {
+        let mut resolver = MoveResolver::new(Torus::new(8, 8));
+        resolver.add_ship(&ship(0, 3, 3), &[(Direction::Still, 0)]);
+        resolver.add_ship(&ship(1, 3, 4), &[(Direction::North, 100), (Direction::East, 5)]);
+
+        let moves = resolver.resolve();
+        assert_eq!(moves[&ShipId(0)], Direction::Still);
+        assert_eq!(moves[&ShipId(1)], Direction::East);
+    }
+
+    #[test]
+    fn ships_stack_only_where_allowed() {
+        let base = Position { x: 0, y: 0 };
+        let ships = [ship(0, 7, 0), ship(1, 1, 0), ship(2, 0, 7)];
+        let candidates = [Direction::East, Direction::West, Direction::South];
+
+        let mut resolver = MoveResolver::new(Torus::new(8, 8));
+        for (ship, &direction) in ships.iter().zip(&candidates) {
+            resolver.add_ship(ship, &[(direction, 10)]);
+        }
+        let moves = resolver.resolve();
+        assert_eq!(moves.values().filter(|&&direction| direction != Direction::Still).count(), 1);
+
+        resolver.allow_stacking(&Position { x: 8, y: 8 });
+        let moves = resolver.resolve();
+        for (ship, &direction) in ships.iter().zip(&candidates) {
+            assert_eq!(moves[&ship.id], direction);
+            assert_eq!(Torus::new(8, 8).offset(&ship.position, direction), base);
+        }
+    }
+}
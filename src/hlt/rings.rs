@@ -0,0 +1,44 @@
+use hlt::position::Position;
+use hlt::torus::Torus;
+
+// Offsets of every cell on the torus, grouped by distance from the origin, so
+// radius queries are a slice lookup. Each cell of the map appears exactly
+// once, which keeps the whole table at width * height entries.
+#[derive(Clone, Debug)]
+pub struct RingTable {
+    offsets: Vec<(i32, i32)>,
+    starts: Vec<usize>,
+}
+
+impl RingTable {
+    pub fn new(torus: Torus) -> RingTable {
+        let origin = Position { x: 0, y: 0 };
+        let max_radius = torus.width / 2 + torus.height / 2;
+
+        let mut offsets: Vec<(i32, i32)> = Vec::with_capacity(torus.width * torus.height);
+        let mut starts: Vec<usize> = Vec::with_capacity(max_radius + 2);
+        for radius in 0..=max_radius {
+            starts.push(offsets.len());
+            offsets.extend(torus.ring(&origin, radius).map(|position| torus.delta(&origin, &position)));
+        }
+        starts.push(offsets.len());
+
+        RingTable { offsets, starts }
+    }
+
+    pub fn max_radius(&self) -> usize {
+        self.starts.len() - 2
+    }
+
+    pub fn ring(&self, radius: usize) -> &[(i32, i32)] {
+        if radius > self.max_radius() {
+            return &[];
+        }
+        &self.offsets[self.starts[radius]..self.starts[radius + 1]]
+    }
+
+    pub fn within(&self, radius: usize) -> &[(i32, i32)] {
+        let end = self.starts[radius.min(self.max_radius()) + 1];
+        &self.offsets[..end]
+    }
+}
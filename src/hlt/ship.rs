@@ -1,7 +1,9 @@
 use hlt::command::Command;
 use hlt::direction::Direction;
 use hlt::entity::Entity;
+use hlt::error::ProtocolError;
 use hlt::input::Input;
+use hlt::log::Log;
 use hlt::PlayerId;
 use hlt::position::Position;
 use hlt::ShipId;
@@ -15,6 +17,10 @@ pub struct Ship {
 }
 
 impl Ship {
+    pub fn new(owner: PlayerId, id: ShipId, position: Position, halite: usize, max_halite: usize) -> Ship {
+        Ship { owner, id, position, halite, max_halite }
+    }
+
     pub fn is_full(&self) -> bool {
         self.halite >= self.max_halite
     }
@@ -32,13 +38,20 @@ impl Ship {
     }
 
     pub fn generate(input: &mut Input, player_id: PlayerId, max_halite: usize) -> Ship {
-        input.read_and_parse_line();
-        let id = ShipId(input.next_usize());
-        let x = input.next_i32();
-        let y = input.next_i32();
-        let halite = input.next_usize();
+        match Ship::try_generate(input, player_id, max_halite) {
+            Ok(ship) => ship,
+            Err(e) => Log::panic(&e.to_string()),
+        }
+    }
+
+    pub fn try_generate(input: &mut Input, player_id: PlayerId, max_halite: usize) -> Result<Ship, ProtocolError> {
+        input.try_read_and_parse_line()?;
+        let id = ShipId(input.try_next_usize()?);
+        let x = input.try_next_i32()?;
+        let y = input.try_next_i32()?;
+        let halite = input.try_next_usize()?;
 
-        Ship { owner: player_id, id, position: Position { x, y }, halite, max_halite }
+        Ok(Ship::new(player_id, id, Position { x, y }, halite, max_halite))
     }
 }
 
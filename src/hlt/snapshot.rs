@@ -0,0 +1,256 @@
+use hlt::constants::Constants;
+use hlt::dropoff::Dropoff;
+use hlt::DropoffId;
+use hlt::game::Game;
+use hlt::game_map::GameMap;
+use hlt::grid::Grid;
+use hlt::json::JsonValue;
+use hlt::map_cell::MapCell;
+use hlt::player::Player;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::ship::Ship;
+use hlt::ShipId;
+use hlt::shipyard::Shipyard;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+// Everything needed to rebuild a `Game` as it was at the start of a turn,
+// as a JSON document. Structures and ship occupancy on the map are derived
+// from the players on load, so only halite is stored per cell.
+pub fn to_json(game: &Game) -> JsonValue {
+    let players = game
+        .players
+        .iter()
+        .map(|player| {
+            let ships = player
+                .ship_ids
+                .iter()
+                .map(|id| {
+                    let ship = &game.ships[id];
+                    object(vec![
+                        ("id", JsonValue::number(ship.id.0)),
+                        ("position", position_to_json(&ship.position)),
+                        ("halite", JsonValue::number(ship.halite)),
+                    ])
+                }).collect();
+
+            let dropoffs = player
+                .dropoff_ids
+                .iter()
+                .map(|id| {
+                    let dropoff = &game.dropoffs[id];
+                    object(vec![
+                        ("id", JsonValue::number(dropoff.id.0)),
+                        ("position", position_to_json(&dropoff.position)),
+                    ])
+                }).collect();
+
+            object(vec![
+                ("id", JsonValue::number(player.id.0)),
+                ("halite", JsonValue::number(player.halite)),
+                ("shipyard", position_to_json(&player.shipyard.position)),
+                ("ships", JsonValue::Array(ships)),
+                ("dropoffs", JsonValue::Array(dropoffs)),
+            ])
+        }).collect();
+
+    let halite = game.map.cells.iter().map(|cell| JsonValue::number(cell.halite)).collect();
+    let map = object(vec![
+        ("width", JsonValue::number(game.map.width)),
+        ("height", JsonValue::number(game.map.height)),
+        ("halite", JsonValue::Array(halite)),
+    ]);
+
+    // The constants serialize themselves; parsing them back can't fail.
+    let constants = JsonValue::parse(&game.constants.to_json()).unwrap();
+
+    object(vec![
+        ("constants", constants),
+        ("turn_number", JsonValue::number(game.turn_number)),
+        ("my_id", JsonValue::number(game.my_id.0)),
+        ("players", JsonValue::Array(players)),
+        ("map", map),
+    ])
+}
+
+pub fn from_json(value: &JsonValue) -> Result<Game, String> {
+    let constants = match Constants::try_new(&field(value, "constants")?.to_string()) {
+        Ok(constants) => constants,
+        Err(e) => return Err(format!("constants: {}", e)),
+    };
+
+    let turn_number = usize_field(value, "turn_number")?;
+    let my_id = PlayerId(usize_field(value, "my_id")?);
+
+    let mut players: Vec<Player> = Vec::new();
+    let mut ships: HashMap<ShipId, Ship> = HashMap::new();
+    let mut dropoffs: HashMap<DropoffId, Dropoff> = HashMap::new();
+
+    for (i, entry) in array_field(value, "players")?.iter().enumerate() {
+        let id = PlayerId(usize_field(entry, "id")?);
+        if id.0 != i {
+            return Err(format!("player {} is listed in position {}", id.0, i));
+        }
+
+        let mut player = Player {
+            id,
+            shipyard: Shipyard { owner: id, position: position_from_json(field(entry, "shipyard")?)? },
+            halite: usize_field(entry, "halite")?,
+            ship_ids: Vec::new(),
+            dropoff_ids: Vec::new(),
+        };
+
+        for ship in array_field(entry, "ships")? {
+            let ship_id = ShipId(usize_field(ship, "id")?);
+            let position = position_from_json(field(ship, "position")?)?;
+            let halite = usize_field(ship, "halite")?;
+
+            player.ship_ids.push(ship_id);
+            ships.insert(ship_id, Ship::new(id, ship_id, position, halite, constants.max_halite));
+        }
+
+        for dropoff in array_field(entry, "dropoffs")? {
+            let dropoff_id = DropoffId(usize_field(dropoff, "id")?);
+            let position = position_from_json(field(dropoff, "position")?)?;
+
+            player.dropoff_ids.push(dropoff_id);
+            dropoffs.insert(dropoff_id, Dropoff { owner: id, id: dropoff_id, position });
+        }
+
+        players.push(player);
+    }
+
+    if my_id.0 >= players.len() {
+        return Err(format!("my_id {} is not one of the {} players", my_id.0, players.len()));
+    }
+
+    let map = field(value, "map")?;
+    let width = usize_field(map, "width")?;
+    let height = usize_field(map, "height")?;
+    if width == 0 || height == 0 {
+        return Err(format!("a map can't be {}x{}", width, height));
+    }
+    let halite = array_field(map, "halite")?;
+    if halite.len() != width * height {
+        return Err(format!("a {}x{} map needs {} halite values but there are {}", width, height, width * height, halite.len()));
+    }
+
+    let halite: Vec<usize> = halite.iter().map(|value| as_usize(value, "halite")).collect::<Result<_, _>>()?;
+    let cells = Grid::from_fn(width, height, |position| {
+        MapCell::new(position, halite[position.y as usize * width + position.x as usize])
+    });
+
+    Ok(Game::from_parts(constants, turn_number, my_id, players, ships, dropoffs, GameMap::new(width, height, cells)))
+}
+
+pub fn save(game: &Game, path: &str) -> io::Result<()> {
+    fs::write(path, format!("{}\n", to_json(game)))
+}
+
+pub fn load(path: &str) -> Result<Game, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return Err(format!("couldn't read {}: {}", path, e)),
+    };
+
+    from_json(&JsonValue::parse(&text)?)
+}
+
+fn object(entries: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+fn position_to_json(position: &Position) -> JsonValue {
+    JsonValue::Array(vec![JsonValue::number(position.x), JsonValue::number(position.y)])
+}
+
+fn position_from_json(value: &JsonValue) -> Result<Position, String> {
+    match value.as_array() {
+        Some([x, y]) => Ok(Position { x: as_usize(x, "x")? as i32, y: as_usize(y, "y")? as i32 }),
+        _ => Err(format!("expected an [x, y] position but found {}", value)),
+    }
+}
+
+fn field<'a>(value: &'a JsonValue, key: &str) -> Result<&'a JsonValue, String> {
+    match value.get(key) {
+        Some(value) => Ok(value),
+        None => Err(format!("missing '{}'", key)),
+    }
+}
+
+fn usize_field(value: &JsonValue, key: &str) -> Result<usize, String> {
+    as_usize(field(value, key)?, key)
+}
+
+fn array_field<'a>(value: &'a JsonValue, key: &str) -> Result<&'a [JsonValue], String> {
+    match field(value, key)?.as_array() {
+        Some(values) => Ok(values),
+        None => Err(format!("'{}' should be an array", key)),
+    }
+}
+
+fn as_usize(value: &JsonValue, what: &str) -> Result<usize, String> {
+    match value.as_usize() {
+        Some(value) => Ok(value),
+        None => Err(format!("'{}' should be a non-negative integer but is {}", what, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &str = r#"{"turn_number": 7, "my_id": 1,
+        "players": [
+            {"id": 0, "halite": 4000, "shipyard": [1, 1], "ships": [{"id": 0, "position": [2, 1], "halite": 350}], "dropoffs": []},
+            {"id": 1, "halite": 3000, "shipyard": [4, 2], "ships": [], "dropoffs": [{"id": 0, "position": [0, 2]}]}
+        ],
+        "map": {"width": 6, "height": 3, "halite": [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17]}}"#;
+
+    // The document above with the constants a real game would have saved.
+    fn snapshot() -> JsonValue {
+        let mut value = JsonValue::parse(SNAPSHOT).unwrap();
+        let constants = JsonValue::parse(&Constants::default_for(6).to_json()).unwrap();
+        if let JsonValue::Object(ref mut entries) = value {
+            entries.insert(0, ("constants".to_string(), constants));
+        }
+        value
+    }
+
+    fn with_map_size(width: usize, height: usize) -> JsonValue {
+        let text = snapshot().to_string().replace("\"width\":6", &format!("\"width\":{}", width));
+        let text = text.replace("\"height\":3", &format!("\"height\":{}", height));
+        JsonValue::parse(&text).unwrap()
+    }
+
+    #[test]
+    fn loads_a_snapshot() {
+        let game = from_json(&snapshot()).unwrap();
+
+        assert_eq!((game.turn_number, game.my_id), (7, PlayerId(1)));
+        assert_eq!((game.map.width, game.map.height), (6, 3));
+        assert_eq!(game.map.at_position(&Position { x: 5, y: 2 }).halite, 17);
+        assert_eq!(game.ships[&ShipId(0)].halite, 350);
+        assert!(game.players[1].dropoff_ids == vec![DropoffId(0)]);
+        assert!(game.map.at_position(&Position { x: 0, y: 2 }).has_friendly_structure(PlayerId(1)));
+    }
+
+    #[test]
+    fn round_trips() {
+        let json = to_json(&from_json(&snapshot()).unwrap());
+        assert_eq!(to_json(&from_json(&json).unwrap()).to_string(), json.to_string());
+    }
+
+    #[test]
+    fn rejects_an_empty_map() {
+        assert_eq!(from_json(&with_map_size(0, 3)).err().unwrap(), "a map can't be 0x3");
+        assert_eq!(from_json(&with_map_size(6, 0)).err().unwrap(), "a map can't be 6x0");
+    }
+
+    #[test]
+    fn rejects_a_map_of_the_wrong_size() {
+        assert!(from_json(&with_map_size(5, 3)).is_err());
+    }
+}
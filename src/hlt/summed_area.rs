@@ -0,0 +1,266 @@
+use hlt::game::Game;
+use hlt::game_map::GameMap;
+use hlt::grid::Grid;
+use hlt::position::Position;
+use std::cmp::min;
+
+// Halite window sums over the map. Box sums come from a summed-area table
+// whose rows from the first changed one down are rebuilt each turn; diamond
+// sums are kept per tracked radius and patched from the map's changed cells.
+pub struct SummedArea {
+    width: usize,
+    height: usize,
+    turn_number: usize,
+    prefix: Vec<usize>,
+    diamonds: Vec<(usize, Grid<usize>)>,
+}
+
+impl SummedArea {
+    pub fn new(game: &Game, diamond_radii: &[usize]) -> SummedArea {
+        let map = &game.map;
+        let mut sums = SummedArea {
+            width: map.width,
+            height: map.height,
+            turn_number: game.turn_number,
+            prefix: vec![0; (map.width + 1) * (map.height + 1)],
+            diamonds: Vec::new(),
+        };
+
+        sums.rebuild_prefix(map, 0);
+        for &radius in diamond_radii {
+            sums.diamonds.push((radius, diamond_sums(map, radius)));
+        }
+
+        sums
+    }
+
+    // Changed cells only cover one turn, so after a skipped frame the
+    // diamonds are recomputed from scratch.
+    pub fn update_frame(&mut self, game: &Game) {
+        if game.turn_number == self.turn_number {
+            return;
+        }
+        let skipped = game.turn_number != self.turn_number + 1;
+        self.turn_number = game.turn_number;
+
+        let map = &game.map;
+        if skipped {
+            self.rebuild_prefix(map, 0);
+            for (radius, diamond) in &mut self.diamonds {
+                *diamond = diamond_sums(map, *radius);
+            }
+            return;
+        }
+
+        // A change reaches every prefix entry below and to the right of it, so
+        // patching cell by cell costs up to a full table per change. Rows above
+        // the first change are untouched, and the rest are rebuilt in one pass.
+        let first_row = match map.changed_cells.iter().map(|change| change.position.y).min() {
+            Some(y) => y as usize,
+            None => return,
+        };
+
+        self.rebuild_prefix(map, first_row);
+        for (radius, diamond) in &mut self.diamonds {
+            for change in &map.changed_cells {
+                for cell in map.cells_within(&change.position, *radius) {
+                    let sum = &mut diamond[cell.position];
+                    *sum = (*sum as i64 + change.delta()) as usize;
+                }
+            }
+        }
+    }
+
+    // Sum over the (2 * radius + 1) square centred on `center`. A square wider
+    // than the map counts every column once.
+    pub fn box_sum(&self, center: &Position, radius: usize) -> usize {
+        let side = 2 * radius + 1;
+        let corner = Position { x: center.x - radius as i32, y: center.y - radius as i32 };
+        self.rect_sum(&corner, side, side)
+    }
+
+    pub fn rect_sum(&self, corner: &Position, width: usize, height: usize) -> usize {
+        let x = corner.x.rem_euclid(self.width as i32) as usize;
+        let y = corner.y.rem_euclid(self.height as i32) as usize;
+        let width = min(width, self.width);
+        let height = min(height, self.height);
+
+        let mut sum = 0;
+        for (x0, x1) in split(x, width, self.width) {
+            for (y0, y1) in split(y, height, self.height) {
+                sum += self.prefix_rect(x0, y0, x1, y1);
+            }
+        }
+        sum
+    }
+
+    // None for a radius that wasn't asked for in `new`.
+    pub fn diamond_sum(&self, center: &Position, radius: usize) -> Option<usize> {
+        self.diamonds
+            .iter()
+            .find(|(tracked, _)| *tracked == radius)
+            .map(|(_, diamond)| *diamond.get(center))
+    }
+
+    pub fn total(&self) -> usize {
+        self.prefix_rect(0, 0, self.width, self.height)
+    }
+
+    // Rows before `first_row` must already be up to date.
+    fn rebuild_prefix(&mut self, map: &GameMap, first_row: usize) {
+        let stride = self.width + 1;
+        for y in first_row..self.height {
+            let mut row_sum = 0;
+            for x in 0..self.width {
+                row_sum += map.cells.get(&Position { x: x as i32, y: y as i32 }).halite;
+                self.prefix[(y + 1) * stride + x + 1] = self.prefix[y * stride + x + 1] + row_sum;
+            }
+        }
+    }
+
+    // Sum over columns x0..x1 and rows y0..y1, neither wrapping.
+    fn prefix_rect(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> usize {
+        let stride = self.width + 1;
+        self.prefix[y1 * stride + x1] + self.prefix[y0 * stride + x0]
+            - self.prefix[y0 * stride + x1]
+            - self.prefix[y1 * stride + x0]
+    }
+}
+
+fn diamond_sums(map: &GameMap, radius: usize) -> Grid<usize> {
+    map.cells.map(|cell| map.cells_within(&cell.position, radius).map(|other| other.halite).sum())
+}
+
+// Splits a wrapped span into two spans that don't cross the edge, the second
+// one empty when the span doesn't wrap.
+fn split(start: usize, length: usize, size: usize) -> [(usize, usize); 2] {
+    if start + length <= size {
+        [(start, start + length), (0, 0)]
+    } else {
+        [(start, size), (0, start + length - size)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::constants::Constants;
+    use hlt::fixtures;
+    use hlt::game_map::CellChange;
+
+    const WIDTH: usize = 11;
+    const HEIGHT: usize = 7;
+
+    fn game() -> Game {
+        let map = fixtures::map(WIDTH, HEIGHT, |position| {
+            let (x, y) = (position.x as usize, position.y as usize);
+            (x * 37 + y * 91) % 113 + x * y
+        });
+        fixtures::game(Constants::default_for(WIDTH), map, &[], Vec::new())
+    }
+
+    fn brute_rect(map: &GameMap, corner: &Position, width: usize, height: usize) -> usize {
+        let mut sum = 0;
+        for dy in 0..min(height, map.height) {
+            for dx in 0..min(width, map.width) {
+                sum += map.at_position(&map.normalize(&Position { x: corner.x + dx as i32, y: corner.y + dy as i32 })).halite;
+            }
+        }
+        sum
+    }
+
+    fn brute_diamond(map: &GameMap, center: &Position, radius: usize) -> usize {
+        map.cells.iter().filter(|cell| map.calculate_distance(center, &cell.position) <= radius).map(|cell| cell.halite).sum()
+    }
+
+    #[test]
+    fn rect_sums_match_brute_force() {
+        let game = game();
+        let sums = SummedArea::new(&game, &[]);
+        let sizes = [(1, 1), (3, 2), (WIDTH, HEIGHT), (WIDTH + 4, 2), (5, HEIGHT + 9)];
+
+        for corner in game.map.cells.positions() {
+            for &(width, height) in &sizes {
+                for &shift in &[0, -(WIDTH as i32) - 3] {
+                    let corner = Position { x: corner.x + shift, y: corner.y - shift };
+                    assert_eq!(sums.rect_sum(&corner, width, height), brute_rect(&game.map, &corner, width, height));
+                }
+            }
+        }
+        assert_eq!(sums.total(), game.map.cells.iter().map(|cell| cell.halite).sum::<usize>());
+    }
+
+    #[test]
+    fn box_sums_match_brute_force() {
+        let game = game();
+        let sums = SummedArea::new(&game, &[]);
+
+        for center in game.map.cells.positions() {
+            for radius in 0..8 {
+                let side = 2 * radius + 1;
+                let corner = Position { x: center.x - radius as i32, y: center.y - radius as i32 };
+                assert_eq!(sums.box_sum(&center, radius), brute_rect(&game.map, &corner, side, side));
+            }
+        }
+    }
+
+    #[test]
+    fn diamond_sums_match_brute_force() {
+        let radii = [0, 1, 3, 9];
+        let game = game();
+        let sums = SummedArea::new(&game, &radii);
+
+        for center in game.map.cells.positions() {
+            for &radius in &radii {
+                assert_eq!(sums.diamond_sum(&center, radius), Some(brute_diamond(&game.map, &center, radius)));
+            }
+        }
+    }
+
+    #[test]
+    fn untracked_radii_have_no_diamond_sums() {
+        let game = game();
+        let sums = SummedArea::new(&game, &[2]);
+        assert_eq!(sums.diamond_sum(&Position { x: 0, y: 0 }, 3), None);
+    }
+
+    #[test]
+    fn updates_follow_changed_cells() {
+        let mut game = game();
+        let mut sums = SummedArea::new(&game, &[2]);
+
+        for &(x, y, halite) in &[(0, 0, 500), (10, 6, 0), (4, 3, 17)] {
+            let position = Position { x, y };
+            let old_halite = game.map.at_position(&position).halite;
+            game.map.at_position_mut(&position).halite = halite;
+            game.map.changed_cells.push(CellChange { position, old_halite, new_halite: halite, miner: None });
+        }
+        game.turn_number += 1;
+        sums.update_frame(&game);
+
+        for center in game.map.cells.positions() {
+            assert_eq!(sums.box_sum(&center, 1), brute_rect(&game.map, &Position { x: center.x - 1, y: center.y - 1 }, 3, 3));
+            assert_eq!(sums.diamond_sum(&center, 2), Some(brute_diamond(&game.map, &center, 2)));
+        }
+    }
+
+    #[test]
+    fn updates_below_the_first_changed_row_only() {
+        let mut game = game();
+        let mut sums = SummedArea::new(&game, &[]);
+
+        for &(x, y, halite) in &[(7, 4, 900), (2, 5, 0)] {
+            let position = Position { x, y };
+            let old_halite = game.map.at_position(&position).halite;
+            game.map.at_position_mut(&position).halite = halite;
+            game.map.changed_cells.push(CellChange { position, old_halite, new_halite: halite, miner: None });
+        }
+        game.turn_number += 1;
+        sums.update_frame(&game);
+
+        for corner in game.map.cells.positions() {
+            assert_eq!(sums.rect_sum(&corner, 4, 3), brute_rect(&game.map, &corner, 4, 3));
+        }
+        assert_eq!(sums.total(), game.map.cells.iter().map(|cell| cell.halite).sum::<usize>());
+    }
+}
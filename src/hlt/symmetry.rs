@@ -0,0 +1,207 @@
+use hlt::direction::Direction;
+use hlt::game::Game;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::torus::Torus;
+
+// A mapping of the map onto itself. Mirrors reflect about the axis through
+// `sum / 2`, so a mirrored coordinate is `sum - c` wrapped.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Transform {
+    Identity,
+    MirrorX { sum: i32 },
+    MirrorY { sum: i32 },
+    Rotate180 { x_sum: i32, y_sum: i32 },
+    Translate { dx: i32, dy: i32 },
+}
+
+impl Transform {
+    pub fn apply(&self, torus: &Torus, position: &Position) -> Position {
+        let Position { x, y } = *position;
+        let mapped = match *self {
+            Transform::Identity => Position { x, y },
+            Transform::MirrorX { sum } => Position { x: sum - x, y },
+            Transform::MirrorY { sum } => Position { x, y: sum - y },
+            Transform::Rotate180 { x_sum, y_sum } => Position { x: x_sum - x, y: y_sum - y },
+            Transform::Translate { dx, dy } => Position { x: x + dx, y: y + dy },
+        };
+        torus.normalize(&mapped)
+    }
+
+    pub fn apply_direction(&self, direction: Direction) -> Direction {
+        let flip_x = matches!(*self, Transform::MirrorX { .. } | Transform::Rotate180 { .. });
+        let flip_y = matches!(*self, Transform::MirrorY { .. } | Transform::Rotate180 { .. });
+
+        match direction {
+            Direction::East | Direction::West if flip_x => direction.invert_direction(),
+            Direction::North | Direction::South if flip_y => direction.invert_direction(),
+            _ => direction,
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        match *self {
+            Transform::Translate { dx, dy } => Transform::Translate { dx: -dx, dy: -dy },
+            transform => transform,
+        }
+    }
+
+    // The ways of taking `from` onto `to` that the map could be built with.
+    fn candidates(from: &Position, to: &Position) -> Vec<Transform> {
+        let mut candidates: Vec<Transform> = Vec::new();
+        if from == to {
+            candidates.push(Transform::Identity);
+        }
+        if from.y == to.y {
+            candidates.push(Transform::MirrorX { sum: from.x + to.x });
+        }
+        if from.x == to.x {
+            candidates.push(Transform::MirrorY { sum: from.y + to.y });
+        }
+        candidates.push(Transform::Rotate180 { x_sum: from.x + to.x, y_sum: from.y + to.y });
+        candidates.push(Transform::Translate { dx: to.x - from.x, dy: to.y - from.y });
+        candidates
+    }
+}
+
+// How the map relates one player's start to every other player's. Detection
+// compares halite cell by cell, so it has to run on the initial map, before
+// any halite has been mined.
+pub struct Symmetry {
+    pub player: PlayerId,
+    torus: Torus,
+    transforms: Vec<Option<Transform>>,
+}
+
+impl Symmetry {
+    pub fn detect(game: &Game, player: PlayerId) -> Symmetry {
+        let torus = game.map.torus();
+        let origin = game.players[player.0].shipyard.position;
+
+        let transforms = game
+            .players
+            .iter()
+            .map(|other| {
+                Transform::candidates(&origin, &other.shipyard.position)
+                    .into_iter()
+                    .find(|transform| preserves_halite(game, &torus, transform))
+            }).collect();
+
+        Symmetry { player, torus, transforms }
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.transforms.iter().all(|transform| transform.is_some())
+    }
+
+    pub fn transform(&self, other: PlayerId) -> Option<Transform> {
+        self.transforms[other.0]
+    }
+
+    // Where `position`, seen from our side of the map, lies on `other`'s side.
+    pub fn to_player(&self, other: PlayerId, position: &Position) -> Option<Position> {
+        self.transform(other).map(|transform| transform.apply(&self.torus, position))
+    }
+
+    // Where a position on `other`'s side lies on ours.
+    pub fn from_player(&self, other: PlayerId, position: &Position) -> Option<Position> {
+        self.transform(other).map(|transform| transform.inverse().apply(&self.torus, position))
+    }
+
+    pub fn equivalents(&self, position: &Position) -> Vec<(PlayerId, Position)> {
+        (0..self.transforms.len())
+            .filter(|&id| id != self.player.0)
+            .filter_map(|id| self.to_player(PlayerId(id), position).map(|mapped| (PlayerId(id), mapped)))
+            .collect()
+    }
+}
+
+fn preserves_halite(game: &Game, torus: &Torus, transform: &Transform) -> bool {
+    game.map
+        .cells
+        .iter()
+        .all(|cell| game.map.at_position(&transform.apply(torus, &cell.position)).halite == cell.halite)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::constants::Constants;
+    use hlt::fixtures;
+    use hlt::fixtures::position;
+
+    // Uneven enough that no other transform keeps it.
+    fn halite(x: i32, y: i32) -> usize {
+        ((x * 37 + y * 91 + x * x * y) % 113) as usize
+    }
+
+    fn two_player_game() -> Game {
+        let map = fixtures::map(16, 8, |p| halite(p.x.min(15 - p.x), p.y));
+        fixtures::game(Constants::default_for(16), map, &[position(4, 3), position(11, 3)], Vec::new())
+    }
+
+    fn four_player_game() -> Game {
+        let map = fixtures::map(16, 16, |p| halite(p.x.min(15 - p.x), p.y.min(15 - p.y)));
+        let shipyards = [position(4, 4), position(11, 4), position(4, 11), position(11, 11)];
+        fixtures::game(Constants::default_for(16), map, &shipyards, Vec::new())
+    }
+
+    #[test]
+    fn detects_a_mirrored_two_player_map() {
+        let symmetry = Symmetry::detect(&two_player_game(), PlayerId(0));
+
+        assert!(symmetry.is_symmetric());
+        assert_eq!(symmetry.transform(PlayerId(0)), Some(Transform::Identity));
+        assert_eq!(symmetry.transform(PlayerId(1)), Some(Transform::MirrorX { sum: 15 }));
+        assert_eq!(symmetry.to_player(PlayerId(1), &position(1, 2)), Some(position(14, 2)));
+        assert_eq!(symmetry.from_player(PlayerId(1), &position(14, 2)), Some(position(1, 2)));
+        assert_eq!(symmetry.equivalents(&position(4, 3)), vec![(PlayerId(1), position(11, 3))]);
+    }
+
+    #[test]
+    fn detects_a_four_player_map() {
+        let game = four_player_game();
+        let symmetry = Symmetry::detect(&game, PlayerId(0));
+
+        assert!(symmetry.is_symmetric());
+        assert_eq!(symmetry.transform(PlayerId(1)), Some(Transform::MirrorX { sum: 15 }));
+        assert_eq!(symmetry.transform(PlayerId(2)), Some(Transform::MirrorY { sum: 15 }));
+        assert_eq!(symmetry.transform(PlayerId(3)), Some(Transform::Rotate180 { x_sum: 15, y_sum: 15 }));
+
+        // Every player's shipyard is where ours maps to.
+        let equivalents = symmetry.equivalents(&game.players[0].shipyard.position);
+        for (player, position) in equivalents {
+            assert_eq!(position, game.players[player.0].shipyard.position);
+        }
+
+        // Seen from another player the same map is symmetric too.
+        let symmetry = Symmetry::detect(&game, PlayerId(3));
+        assert_eq!(symmetry.to_player(PlayerId(0), &position(11, 11)), Some(position(4, 4)));
+    }
+
+    #[test]
+    fn finds_nothing_on_an_uneven_map() {
+        let map = fixtures::map(16, 8, |p| halite(p.x, p.y));
+        let game = fixtures::game(Constants::default_for(16), map, &[position(4, 3), position(11, 3)], Vec::new());
+        let symmetry = Symmetry::detect(&game, PlayerId(0));
+
+        assert!(!symmetry.is_symmetric());
+        assert_eq!(symmetry.to_player(PlayerId(1), &position(0, 0)), None);
+        assert!(symmetry.equivalents(&position(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn transforms_turn_directions_with_the_map() {
+        let mirror = Transform::MirrorX { sum: 15 };
+        assert_eq!(mirror.apply_direction(Direction::East), Direction::West);
+        assert_eq!(mirror.apply_direction(Direction::North), Direction::North);
+
+        let rotate = Transform::Rotate180 { x_sum: 15, y_sum: 15 };
+        assert_eq!(rotate.apply_direction(Direction::South), Direction::North);
+
+        let torus = Torus::new(16, 8);
+        let shift = Transform::Translate { dx: 7, dy: -3 };
+        assert_eq!(shift.inverse().apply(&torus, &shift.apply(&torus, &position(12, 1))), position(12, 1));
+        assert_eq!(shift.apply_direction(Direction::West), Direction::West);
+    }
+}
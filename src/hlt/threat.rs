@@ -0,0 +1,249 @@
+use hlt::game::Game;
+use hlt::grid::Grid;
+use hlt::PlayerId;
+use hlt::position::Position;
+use std::cmp::max;
+use std::cmp::min;
+
+// How much contact with enemy ships a move may risk.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RiskPolicy {
+    // Stay off every cell an enemy could reach.
+    Avoid,
+    // Allow cells where a collision would cost the enemy at least as much.
+    Trade,
+    // Like Trade, but seek those cells out.
+    Hunt,
+}
+
+// The enemy ships that could end next turn on a cell.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Threat {
+    pub ships: usize,
+    pub min_cargo: usize,
+    pub max_cargo: usize,
+}
+
+// Where enemy ships could be at the end of this turn, assuming each either
+// stays or makes one move it can afford.
+pub struct ThreatMap {
+    pub player: PlayerId,
+    move_cost_ratio: usize,
+    // A trade has to win us at least this much before it's worth taking.
+    trade_margin: i64,
+    threats: Grid<Option<Threat>>,
+    near_base: Grid<bool>,
+    own_base: Grid<bool>,
+}
+
+impl ThreatMap {
+    pub fn new(game: &Game, player: PlayerId) -> ThreatMap {
+        let constants = &game.constants;
+        let width = game.map.width;
+        let height = game.map.height;
+
+        // With more than two players, losing a ship for one of theirs still
+        // leaves us behind everyone else, so the enemy has to lose more.
+        let rivals = game.players.len().saturating_sub(1).max(1);
+        let trade_margin = if rivals > 1 { (constants.ship_cost / rivals) as i64 } else { 0 };
+
+        ThreatMap {
+            player,
+            // Inspiration can make moving cheaper; assume the cheapest rate.
+            move_cost_ratio: max(constants.move_cost_ratio, constants.inspired_move_cost_ratio),
+            trade_margin,
+            threats: Grid::new(width, height, None),
+            near_base: Grid::new(width, height, false),
+            own_base: Grid::new(width, height, false),
+        }
+    }
+
+    pub fn update_frame(&mut self, game: &Game) {
+        self.threats.fill(None);
+        self.near_base.fill(false);
+        self.own_base.fill(false);
+
+        for cell in &game.map.cells {
+            if cell.has_friendly_structure(self.player) {
+                self.own_base.set(&cell.position, true);
+                for neighbour in game.map.cells_within(&cell.position, 1) {
+                    self.near_base.set(&neighbour.position, true);
+                }
+            }
+        }
+
+        let torus = game.map.torus();
+        let player = self.player;
+        for ship in game.ships.values().filter(|ship| ship.owner != player) {
+            let mut reachable = vec![ship.position];
+            if ship.halite >= game.map.at_position(&ship.position).halite / self.move_cost_ratio {
+                reachable.extend(self.threats.neighbours(&ship.position));
+            }
+
+            for position in reachable {
+                let position = torus.normalize(&position);
+                let threat = match *self.threats.get(&position) {
+                    Some(threat) => Threat {
+                        ships: threat.ships + 1,
+                        min_cargo: min(threat.min_cargo, ship.halite),
+                        max_cargo: max(threat.max_cargo, ship.halite),
+                    },
+                    None => Threat { ships: 1, min_cargo: ship.halite, max_cargo: ship.halite },
+                };
+                self.threats.set(&position, Some(threat));
+            }
+        }
+    }
+
+    pub fn threat(&self, position: &Position) -> Option<Threat> {
+        *self.threats.get(position)
+    }
+
+    pub fn is_threatened(&self, position: &Position) -> bool {
+        self.threat(position).is_some()
+    }
+
+    pub fn is_near_base(&self, position: &Position) -> bool {
+        *self.near_base.get(position)
+    }
+
+    // What colliding on `position` while carrying `cargo` wins us over the
+    // enemy that loses least, or None when no enemy can get there. Whatever
+    // lands on our own base is ours, and what spills next to it we'll most
+    // likely pick up.
+    pub fn trade_value(&self, cargo: usize, position: &Position) -> Option<i64> {
+        self.threat(position).map(|threat| self.value_against(&threat, cargo, threat.min_cargo, position))
+    }
+
+    fn value_against(&self, threat: &Threat, cargo: usize, enemy: usize, position: &Position) -> i64 {
+        let (cargo, enemy) = (cargo as i64, enemy as i64);
+        if *self.own_base.get(position) {
+            return cargo + enemy;
+        }
+
+        // Any other enemy in reach ends up next to the spill, so it's as
+        // likely to be theirs as ours.
+        let spill = cargo + enemy;
+        let contested = if threat.ships > 1 { spill / 2 } else { 0 };
+        let ours = if self.is_near_base(position) { spill / 2 } else { 0 };
+        enemy - cargo + ours - contested - self.trade_margin
+    }
+
+    // None when the policy rules the move out, otherwise what to add to its
+    // value. Even Avoid lets ships onto our own base. Whether a trade is safe
+    // is judged by the poorest enemy in reach, but hunters go for the richest.
+    pub fn adjust(&self, policy: RiskPolicy, cargo: usize, position: &Position) -> Option<i64> {
+        let threat = match self.threat(position) {
+            Some(threat) => threat,
+            None => return Some(0),
+        };
+
+        match policy {
+            _ if self.value_against(&threat, cargo, threat.min_cargo, position) < 0 => None,
+            RiskPolicy::Avoid if !*self.own_base.get(position) => None,
+            RiskPolicy::Hunt => Some(self.value_against(&threat, cargo, threat.max_cargo, position)),
+            _ => Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::constants::Constants;
+    use hlt::fixtures;
+    use hlt::fixtures::position;
+    use hlt::fixtures::ship;
+
+    // We're player 0 with a shipyard at (2, 2); the other shipyards are out
+    // of the way. Every `enemies` ship belongs to player 1.
+    fn threats(player_count: usize, enemies: &[(i32, i32, usize)]) -> ThreatMap {
+        let shipyards = [position(2, 2), position(12, 12), position(2, 12), position(12, 2)];
+        let ships = enemies.iter().enumerate().map(|(i, &(x, y, halite))| ship(1, i, x, y, halite)).collect();
+        let map = fixtures::map(16, 16, |_| 0);
+        let game = fixtures::game(Constants::default_for(16), map, &shipyards[..player_count], ships);
+
+        let mut threats = ThreatMap::new(&game, PlayerId(0));
+        threats.update_frame(&game);
+        threats
+    }
+
+    #[test]
+    fn marks_where_enemies_can_reach() {
+        let threats = threats(2, &[(8, 8, 500), (10, 8, 900)]);
+
+        assert_eq!(threats.threat(&position(7, 8)), Some(Threat { ships: 1, min_cargo: 500, max_cargo: 500 }));
+        assert_eq!(threats.threat(&position(9, 8)), Some(Threat { ships: 2, min_cargo: 500, max_cargo: 900 }));
+        assert!(!threats.is_threatened(&position(8, 10)));
+    }
+
+    #[test]
+    fn avoid_keeps_off_every_threatened_cell_but_our_base() {
+        let threats = threats(2, &[(8, 8, 500), (2, 3, 0)]);
+
+        assert_eq!(threats.adjust(RiskPolicy::Avoid, 0, &position(9, 8)), None);
+        assert_eq!(threats.adjust(RiskPolicy::Avoid, 0, &position(5, 5)), Some(0));
+        assert_eq!(threats.adjust(RiskPolicy::Avoid, 300, &position(2, 2)), Some(0));
+    }
+
+    #[test]
+    fn trade_takes_only_collisions_that_cost_the_enemy_more() {
+        let threats = threats(2, &[(8, 8, 500)]);
+
+        assert_eq!(threats.trade_value(300, &position(9, 8)), Some(200));
+        assert_eq!(threats.adjust(RiskPolicy::Trade, 300, &position(9, 8)), Some(0));
+        assert_eq!(threats.adjust(RiskPolicy::Trade, 800, &position(9, 8)), None);
+    }
+
+    #[test]
+    fn a_second_attacker_makes_a_trade_worse() {
+        let threats = threats(2, &[(8, 8, 500), (10, 8, 500)]);
+
+        assert_eq!(threats.trade_value(300, &position(7, 8)), Some(200));
+        assert_eq!(threats.trade_value(300, &position(9, 8)), Some(-200));
+        assert_eq!(threats.adjust(RiskPolicy::Trade, 300, &position(9, 8)), None);
+    }
+
+    #[test]
+    fn hunt_goes_for_the_richest_ship_in_reach() {
+        let threats = threats(2, &[(8, 8, 500), (10, 8, 900)]);
+
+        assert_eq!(threats.adjust(RiskPolicy::Hunt, 0, &position(7, 8)), Some(500));
+        assert_eq!(threats.adjust(RiskPolicy::Hunt, 0, &position(9, 8)), Some(450));
+        assert_eq!(threats.adjust(RiskPolicy::Hunt, 0, &position(5, 5)), Some(0));
+        assert_eq!(threats.adjust(RiskPolicy::Hunt, 800, &position(7, 8)), None);
+    }
+
+    #[test]
+    fn spills_next_to_our_base_count_as_ours() {
+        // (2, 3) is next to the shipyard, (2, 5) isn't.
+        let alone = threats(2, &[(2, 4, 100)]);
+        assert!(alone.is_near_base(&position(2, 3)));
+        assert_eq!(alone.trade_value(300, &position(2, 5)), Some(-200));
+        assert_eq!(alone.trade_value(300, &position(2, 3)), Some(0));
+
+        // Unless another enemy is there to take them.
+        let contested = threats(2, &[(2, 4, 100), (3, 3, 100)]);
+        assert_eq!(contested.trade_value(300, &position(2, 3)), Some(-200));
+    }
+
+    #[test]
+    fn our_base_is_always_worth_defending() {
+        let threats = threats(2, &[(2, 3, 700)]);
+
+        assert_eq!(threats.trade_value(300, &position(2, 2)), Some(1000));
+        assert_eq!(threats.adjust(RiskPolicy::Trade, 300, &position(2, 2)), Some(0));
+        assert_eq!(threats.adjust(RiskPolicy::Avoid, 300, &position(2, 2)), Some(0));
+    }
+
+    #[test]
+    fn four_players_ask_more_of_a_trade() {
+        let two = threats(2, &[(8, 8, 300)]);
+        let four = threats(4, &[(8, 8, 300)]);
+
+        assert_eq!(two.trade_value(0, &position(9, 8)), Some(300));
+        assert_eq!(four.trade_value(0, &position(9, 8)), Some(300 - 1000 / 3));
+        assert_eq!(two.adjust(RiskPolicy::Trade, 0, &position(9, 8)), Some(0));
+        assert_eq!(four.adjust(RiskPolicy::Trade, 0, &position(9, 8)), None);
+    }
+}
@@ -0,0 +1,210 @@
+use hlt::direction::Direction;
+use hlt::position::Position;
+use std::ops::Add;
+use std::ops::Sub;
+
+// Wrap-around geometry of a map. Everything that needs shortest distances or
+// directions across the edges should go through here.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Torus {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Torus {
+    pub fn new(width: usize, height: usize) -> Torus {
+        Torus { width, height }
+    }
+
+    pub fn normalize(&self, position: &Position) -> Position {
+        Position { x: wrap(position.x, self.width), y: wrap(position.y, self.height) }
+    }
+
+    pub fn wrap(&self, position: &Position) -> Wrapped {
+        Wrapped { torus: *self, position: self.normalize(position) }
+    }
+
+    pub fn offset(&self, position: &Position, direction: Direction) -> Position {
+        self.normalize(&position.directional_offset(direction))
+    }
+
+    // Signed shortest (dx, dy) from source to target. When both ways around
+    // are equally long the positive one is used.
+    pub fn delta(&self, source: &Position, target: &Position) -> (i32, i32) {
+        (
+            shortest(target.x - source.x, self.width),
+            shortest(target.y - source.y, self.height),
+        )
+    }
+
+    pub fn distance(&self, source: &Position, target: &Position) -> usize {
+        let (dx, dy) = self.delta(source, target);
+        (dx.unsigned_abs() + dy.unsigned_abs()) as usize
+    }
+
+    // Every direction that brings source closer to target, horizontal first.
+    pub fn directions_toward(&self, source: &Position, target: &Position) -> Vec<Direction> {
+        let (dx, dy) = self.delta(source, target);
+        let mut directions: Vec<Direction> = Vec::with_capacity(2);
+
+        if dx > 0 {
+            directions.push(Direction::East);
+        } else if dx < 0 {
+            directions.push(Direction::West);
+        }
+
+        if dy > 0 {
+            directions.push(Direction::South);
+        } else if dy < 0 {
+            directions.push(Direction::North);
+        }
+
+        directions
+    }
+
+    // Positions at exactly `radius` from center, each listed once even when
+    // the ring wraps onto itself on a small map.
+    pub fn ring(&self, center: &Position, radius: usize) -> impl Iterator<Item = Position> {
+        let torus = *self;
+        let center = torus.normalize(center);
+        let r = radius as i32;
+
+        (-r..=r)
+            .flat_map(move |dx| {
+                let dy = r - dx.abs();
+                let second = if dy == 0 { None } else { Some((dx, -dy)) };
+                Some((dx, dy)).into_iter().chain(second)
+            })
+            .filter_map(move |(dx, dy)| {
+                let position = torus.normalize(&Position { x: center.x + dx, y: center.y + dy });
+                if torus.delta(&center, &position) == (dx, dy) {
+                    Some(position)
+                } else {
+                    None
+                }
+            })
+    }
+
+    // Positions within `radius` of center, nearest rings first.
+    pub fn diamond(&self, center: &Position, radius: usize) -> impl Iterator<Item = Position> {
+        let torus = *self;
+        let center = *center;
+        (0..=radius).flat_map(move |r| torus.ring(&center, r))
+    }
+}
+
+// A position tied to the torus it lives on, so that arithmetic on it wraps.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Wrapped {
+    pub torus: Torus,
+    pub position: Position,
+}
+
+impl Add<Direction> for Wrapped {
+    type Output = Wrapped;
+
+    fn add(self, direction: Direction) -> Wrapped {
+        Wrapped { torus: self.torus, position: self.torus.offset(&self.position, direction) }
+    }
+}
+
+// The shortest signed (dx, dy) that takes `other` to `self`.
+impl Sub for Wrapped {
+    type Output = (i32, i32);
+
+    fn sub(self, other: Wrapped) -> (i32, i32) {
+        self.torus.delta(&other.position, &self.position)
+    }
+}
+
+impl From<Wrapped> for Position {
+    fn from(wrapped: Wrapped) -> Position {
+        wrapped.position
+    }
+}
+
+fn wrap(coordinate: i32, size: usize) -> i32 {
+    coordinate.rem_euclid(size as i32)
+}
+
+fn shortest(delta: i32, size: usize) -> i32 {
+    let delta = wrap(delta, size);
+    if 2 * delta > size as i32 {
+        delta - size as i32
+    } else {
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::fixtures::position;
+    use std::collections::HashSet;
+
+    #[test]
+    fn normalizes_any_position_onto_the_map() {
+        let torus = Torus::new(8, 6);
+        assert_eq!(torus.normalize(&position(3, 2)), position(3, 2));
+        assert_eq!(torus.normalize(&position(-1, -1)), position(7, 5));
+        assert_eq!(torus.normalize(&position(8, 6)), position(0, 0));
+        assert_eq!(torus.normalize(&position(-17, 13)), position(7, 1));
+        assert_eq!(torus.offset(&position(0, 0), Direction::North), position(0, 5));
+        assert_eq!(torus.offset(&position(7, 0), Direction::East), position(0, 0));
+    }
+
+    #[test]
+    fn deltas_take_the_short_way_round() {
+        let torus = Torus::new(8, 6);
+        assert_eq!(torus.delta(&position(1, 1), &position(3, 2)), (2, 1));
+        assert_eq!(torus.delta(&position(0, 0), &position(7, 5)), (-1, -1));
+        assert_eq!(torus.delta(&position(7, 5), &position(0, 0)), (1, 1));
+        // Halfway round either way counts as positive.
+        assert_eq!(torus.delta(&position(0, 0), &position(4, 3)), (4, 3));
+        assert_eq!(torus.delta(&position(4, 3), &position(0, 0)), (4, 3));
+        assert_eq!(torus.delta(&position(-8, 12), &position(1, 1)), (1, 1));
+        assert_eq!(Position::from(torus.wrap(&position(-8, 6))), position(0, 0));
+        assert_eq!(torus.wrap(&position(7, 5)) - torus.wrap(&position(0, 0)), (-1, -1));
+    }
+
+    #[test]
+    fn distances_wrap_around_the_edges() {
+        let torus = Torus::new(8, 6);
+        assert_eq!(torus.distance(&position(0, 0), &position(7, 5)), 2);
+        assert_eq!(torus.distance(&position(1, 0), &position(6, 0)), 3);
+        assert_eq!(torus.distance(&position(0, 0), &position(4, 3)), 7);
+        assert_eq!(torus.distance(&position(2, 2), &position(2 + 8, 2 - 12)), 0);
+        assert_eq!(torus.directions_toward(&position(0, 0), &position(7, 1)), vec![Direction::West, Direction::South]);
+        assert!(torus.directions_toward(&position(3, 3), &position(11, -3)).is_empty());
+    }
+
+    #[test]
+    fn rings_hold_each_cell_at_that_distance_once() {
+        for &(width, height) in &[(8, 6), (5, 5), (3, 4), (1, 1)] {
+            let torus = Torus::new(width, height);
+            for &center in &[position(0, 0), position(2, 1), position(-1, 9)] {
+                for radius in 0..8 {
+                    let ring: Vec<Position> = torus.ring(&center, radius).collect();
+                    let unique: HashSet<Position> = ring.iter().cloned().collect();
+                    assert_eq!(ring.len(), unique.len());
+
+                    let expected: HashSet<Position> = (0..width as i32)
+                        .flat_map(|x| (0..height as i32).map(move |y| position(x, y)))
+                        .filter(|other| torus.distance(&center, other) == radius)
+                        .collect();
+                    assert_eq!(unique, expected, "{}x{} ring of {} around {}", width, height, radius, center);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn diamonds_go_outward_ring_by_ring() {
+        let torus = Torus::new(8, 6);
+        let diamond: Vec<Position> = torus.diamond(&position(0, 0), 2).collect();
+        assert_eq!(diamond.len(), 1 + 4 + 8);
+        assert_eq!(diamond[0], position(0, 0));
+        let distances: Vec<usize> = diamond.iter().map(|other| torus.distance(&position(0, 0), other)).collect();
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+}
@@ -0,0 +1,295 @@
+use hlt::direction::Direction;
+use hlt::game::Game;
+use hlt::PlayerId;
+use hlt::position::Position;
+use hlt::ship::Ship;
+use hlt::ShipId;
+use hlt::torus::Torus;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+// The cells around a base split into lanes for ships coming in and ships
+// going out, given as the sides of the base they lie on. Lanes reach as far
+// as the controller's radius and are only kept while ships are on their way
+// in.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Lanes {
+    pub base: Position,
+    pub arrivals: usize,
+    pub inbound: Vec<Direction>,
+    pub outbound: Vec<Direction>,
+}
+
+impl Lanes {
+    fn contains(&self, torus: &Torus, radius: usize, directions: &[Direction], position: &Position) -> bool {
+        let distance = torus.distance(&self.base, position);
+        distance > 0 && distance <= radius && directions.contains(&side(torus, &self.base, position))
+    }
+}
+
+// Keeps the cells around our bases moving. Returning ships come in through
+// the sides most of them approach from and take the base in order of arrival;
+// everyone else leaves through the other sides and keeps off the base. When
+// we want to spawn, the shipyard is held for the new ship, though never two
+// turns running while a returning ship waits next to it.
+pub struct TrafficController {
+    pub player: PlayerId,
+    torus: Torus,
+    radius: usize,
+    // What breaking a traffic rule costs, in the same halite terms as moves.
+    weight: i64,
+    lanes: Vec<Lanes>,
+    queue: HashMap<ShipId, usize>,
+    // Which of `lanes` each of our ships near a base follows.
+    nearest: HashMap<ShipId, usize>,
+    holding_shipyard: bool,
+}
+
+impl TrafficController {
+    pub fn new(game: &Game, player: PlayerId, radius: usize) -> TrafficController {
+        TrafficController {
+            player,
+            torus: game.map.torus(),
+            radius,
+            weight: game.constants.max_halite as i64,
+            lanes: Vec::new(),
+            queue: HashMap::new(),
+            nearest: HashMap::new(),
+            holding_shipyard: false,
+        }
+    }
+
+    pub fn update_frame(&mut self, game: &Game, returning: &[ShipId], wants_spawn: bool) {
+        let me = &game.players[self.player.0];
+        // The shipyard comes first, so it's always lanes[0].
+        let mut bases: Vec<Position> = vec![me.shipyard.position];
+        let mut dropoff_ids = me.dropoff_ids.clone();
+        dropoff_ids.sort_by_key(|id| id.0);
+        bases.extend(dropoff_ids.iter().map(|id| game.dropoffs[id].position));
+
+        self.nearest.clear();
+        for ship_id in &me.ship_ids {
+            if let Some((index, _)) = self.nearest_base(&bases, &game.ships[ship_id].position) {
+                self.nearest.insert(*ship_id, index);
+            }
+        }
+
+        // Returning ships close to each base, soonest arrival first.
+        let mut arrivals: Vec<Vec<(usize, ShipId, Position)>> = vec![Vec::new(); bases.len()];
+        for ship_id in returning {
+            if let Some(&index) = self.nearest.get(ship_id) {
+                let position = game.ships[ship_id].position;
+                arrivals[index].push((self.torus.distance(&bases[index], &position), *ship_id, position));
+            }
+        }
+
+        self.lanes.clear();
+        self.queue.clear();
+        for (base, arrivals) in bases.into_iter().zip(arrivals.iter_mut()) {
+            arrivals.sort_by_key(|&(distance, ship_id, _)| (distance, ship_id.0));
+            for (i, &(_, ship_id, _)) in arrivals.iter().enumerate() {
+                self.queue.insert(ship_id, i);
+            }
+
+            let torus = &self.torus;
+            let mut sides: Vec<(usize, Direction)> = Direction::get_all_cardinals()
+                .into_iter()
+                .map(|direction| {
+                    let count = arrivals
+                        .iter()
+                        .filter(|&&(_, _, position)| side(torus, &base, &position) == direction)
+                        .count();
+                    (count, direction)
+                }).collect();
+            // Stable, so ties keep the cardinal order.
+            sides.sort_by_key(|&(count, _)| Reverse(count));
+
+            let inbound = sides.iter().take(2).map(|&(_, direction)| direction).collect();
+            let outbound = sides.iter().skip(2).map(|&(_, direction)| direction).collect();
+            self.lanes.push(Lanes { base, arrivals: arrivals.len(), inbound, outbound });
+        }
+
+        // A spawn and an arrival can't share the shipyard, so when both want
+        // it they take turns.
+        let waiting = arrivals[0].first().map(|&(distance, _, _)| distance <= 1).unwrap_or(false);
+        self.holding_shipyard = wants_spawn && !(self.holding_shipyard && waiting);
+    }
+
+    pub fn lanes(&self) -> &[Lanes] {
+        &self.lanes
+    }
+
+    // Where a returning ship stands in line for its base, 0 being next.
+    pub fn queue_position(&self, ship_id: ShipId) -> Option<usize> {
+        self.queue.get(&ship_id).cloned()
+    }
+
+    // Whether the shipyard is kept free this turn for a new ship.
+    pub fn holding_shipyard(&self) -> bool {
+        self.holding_shipyard
+    }
+
+    // What to add to the value of a move under the traffic rules. Ships away
+    // from our bases are left alone. A ship already in the wrong lane may
+    // carry on its way out of it.
+    pub fn adjust(&self, ship: &Ship, returning: bool, direction: Direction) -> i64 {
+        let index = match self.nearest.get(&ship.id) {
+            Some(&index) => index,
+            None => return 0,
+        };
+        let lanes = &self.lanes[index];
+
+        let target = self.torus.offset(&ship.position, direction);
+        let busy = lanes.arrivals > 0;
+        let (wrong_lane, heading_in) = if returning { (&lanes.outbound, true) } else { (&lanes.inbound, false) };
+        let crossing = busy
+            && lanes.contains(&self.torus, self.radius, wrong_lane, &target)
+            && !(lanes.contains(&self.torus, self.radius, wrong_lane, &ship.position)
+                && self.moves_inward(lanes, &ship.position, &target) == Some(heading_in));
+
+        if target == lanes.base {
+            if !returning || (index == 0 && self.holding_shipyard) {
+                -self.weight
+            } else {
+                // Earlier arrivals win the base; the rest wait their turn.
+                let position = self.queue_position(ship.id).unwrap_or(0).min(32);
+                self.weight >> position
+            }
+        } else if crossing {
+            -self.weight / 2
+        } else {
+            0
+        }
+    }
+
+    // Some(true) for a step toward the base, Some(false) for one away from
+    // it and None for staying as far.
+    fn moves_inward(&self, lanes: &Lanes, from: &Position, to: &Position) -> Option<bool> {
+        let before = self.torus.distance(&lanes.base, from);
+        let after = self.torus.distance(&lanes.base, to);
+        if after == before { None } else { Some(after < before) }
+    }
+
+    fn nearest_base(&self, bases: &[Position], position: &Position) -> Option<(usize, usize)> {
+        bases
+            .iter()
+            .map(|base| self.torus.distance(base, position))
+            .enumerate()
+            .filter(|&(_, distance)| distance <= self.radius)
+            .min_by_key(|&(_, distance)| distance)
+    }
+}
+
+// Which side of the base a position is on, by the longer axis.
+fn side(torus: &Torus, base: &Position, position: &Position) -> Direction {
+    let (dx, dy) = torus.delta(base, position);
+    if dx.abs() >= dy.abs() {
+        if dx >= 0 { Direction::East } else { Direction::West }
+    } else if dy >= 0 {
+        Direction::South
+    } else {
+        Direction::North
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::constants::Constants;
+    use hlt::fixtures;
+    use hlt::fixtures::position;
+    use hlt::fixtures::ship;
+
+    const WEIGHT: i64 = 1000;
+
+    // Our shipyard is at (8, 8); the other player's is out of the way. All
+    // `ships` are ours.
+    fn game(ships: &[(usize, i32, i32)]) -> Game {
+        let ships = ships.iter().map(|&(id, x, y)| ship(0, id, x, y, 500)).collect();
+        let map = fixtures::map(16, 16, |_| 0);
+        fixtures::game(Constants::default_for(16), map, &[position(8, 8), position(0, 0)], ships)
+    }
+
+    fn controller(game: &Game, returning: &[usize], wants_spawn: bool) -> TrafficController {
+        let returning: Vec<ShipId> = returning.iter().map(|&id| ShipId(id)).collect();
+        let mut traffic = TrafficController::new(game, PlayerId(0), 3);
+        traffic.update_frame(game, &returning, wants_spawn);
+        traffic
+    }
+
+    #[test]
+    fn queues_returning_ships_by_distance() {
+        let game = game(&[(1, 8, 10), (2, 9, 8), (3, 7, 8), (4, 8, 14)]);
+        let traffic = controller(&game, &[1, 2, 3, 4], false);
+
+        assert_eq!(traffic.queue_position(ShipId(2)), Some(0));
+        assert_eq!(traffic.queue_position(ShipId(3)), Some(1));
+        assert_eq!(traffic.queue_position(ShipId(1)), Some(2));
+        assert_eq!(traffic.queue_position(ShipId(4)), None);
+        assert_eq!(traffic.lanes()[0].arrivals, 3);
+    }
+
+    #[test]
+    fn lanes_follow_where_ships_come_from() {
+        let game = game(&[(1, 10, 8), (2, 10, 9), (3, 8, 10)]);
+        let traffic = controller(&game, &[1, 2, 3], false);
+
+        let lanes = &traffic.lanes()[0];
+        assert_eq!(lanes.base, position(8, 8));
+        assert_eq!(lanes.inbound, vec![Direction::East, Direction::South]);
+        assert_eq!(lanes.outbound, vec![Direction::North, Direction::West]);
+    }
+
+    #[test]
+    fn earlier_arrivals_win_the_base() {
+        let game = game(&[(1, 9, 8), (2, 8, 9), (3, 7, 8)]);
+        let traffic = controller(&game, &[1, 2], false);
+
+        assert_eq!(traffic.adjust(&game.ships[&ShipId(1)], true, Direction::West), WEIGHT);
+        assert_eq!(traffic.adjust(&game.ships[&ShipId(2)], true, Direction::North), WEIGHT / 2);
+        assert_eq!(traffic.adjust(&game.ships[&ShipId(3)], false, Direction::East), -WEIGHT);
+    }
+
+    #[test]
+    fn lanes_reach_out_to_the_radius() {
+        // Ships come in from the east and south, so north and west are the
+        // way out.
+        let game = game(&[(1, 11, 8), (2, 10, 9), (3, 9, 7), (4, 8, 11), (5, 8, 10), (6, 3, 8)]);
+        let traffic = controller(&game, &[1, 2, 3, 4], false);
+        assert_eq!(traffic.lanes()[0].outbound, vec![Direction::North, Direction::West]);
+
+        // A returning ship keeps out of the way out, even three cells from
+        // the base.
+        assert_eq!(traffic.adjust(&game.ships[&ShipId(3)], true, Direction::North), -WEIGHT / 2);
+        assert_eq!(traffic.adjust(&game.ships[&ShipId(3)], true, Direction::South), 0);
+        // A collector in the way in may only head out through it.
+        assert_eq!(traffic.adjust(&game.ships[&ShipId(5)], false, Direction::North), -WEIGHT / 2);
+        assert_eq!(traffic.adjust(&game.ships[&ShipId(5)], false, Direction::South), 0);
+        // Past the radius nothing applies.
+        assert_eq!(traffic.adjust(&game.ships[&ShipId(6)], false, Direction::East), 0);
+    }
+
+    #[test]
+    fn spawns_and_arrivals_take_turns_at_the_shipyard() {
+        let game = game(&[(1, 9, 8)]);
+        let ship = &game.ships[&ShipId(1)];
+        let mut traffic = controller(&game, &[1], true);
+
+        assert!(traffic.holding_shipyard());
+        assert_eq!(traffic.adjust(ship, true, Direction::West), -WEIGHT);
+
+        traffic.update_frame(&game, &[ShipId(1)], true);
+        assert!(!traffic.holding_shipyard());
+        assert_eq!(traffic.adjust(ship, true, Direction::West), WEIGHT);
+
+        traffic.update_frame(&game, &[ShipId(1)], true);
+        assert!(traffic.holding_shipyard());
+
+        // Without anyone waiting the shipyard stays held.
+        traffic.update_frame(&game, &[], true);
+        traffic.update_frame(&game, &[], true);
+        assert!(traffic.holding_shipyard());
+        traffic.update_frame(&game, &[], false);
+        assert!(!traffic.holding_shipyard());
+    }
+}
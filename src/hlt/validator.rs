@@ -0,0 +1,218 @@
+use hlt::command::Command;
+use hlt::direction::Direction;
+use hlt::game::Game;
+use hlt::inspiration::move_cost_ratio_among;
+use hlt::position::Position;
+use hlt::ShipId;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CommandIssue {
+    DuplicateCommand(ShipId),
+    NotOwned(ShipId),
+    CannotAffordMove { ship_id: ShipId, cost: usize, halite: usize },
+    ConstructOnStructure(ShipId),
+    DuplicateSpawn,
+    CannotAffordSpawn { cost: usize, halite: usize },
+    ShipyardClaimed,
+    Overspent { spent: usize, halite: usize },
+}
+
+impl fmt::Display for CommandIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandIssue::DuplicateCommand(ship_id) => write!(f, "more than one command for ship {}", ship_id.0),
+            CommandIssue::NotOwned(ship_id) => write!(f, "ship {} is not ours", ship_id.0),
+            CommandIssue::CannotAffordMove { ship_id, cost, halite } => {
+                write!(f, "ship {} has {} halite but moving costs {}", ship_id.0, halite, cost)
+            }
+            CommandIssue::ConstructOnStructure(ship_id) => write!(f, "ship {} can't construct on a structure", ship_id.0),
+            CommandIssue::DuplicateSpawn => write!(f, "more than one spawn"),
+            CommandIssue::CannotAffordSpawn { cost, halite } => write!(f, "spawn costs {} but we have {}", cost, halite),
+            CommandIssue::ShipyardClaimed => write!(f, "spawn onto a shipyard one of our ships will occupy"),
+            CommandIssue::Overspent { spent, halite } => write!(f, "spending {} halite but we have {}", spent, halite),
+        }
+    }
+}
+
+// Checks a turn's commands against the current snapshot, the same way the
+// engine will, and reports everything the engine would drop or punish.
+pub fn validate(game: &Game, commands: &[Command]) -> Vec<CommandIssue> {
+    let mut issues: Vec<CommandIssue> = Vec::new();
+    let me = &game.players[game.my_id.0];
+    let constants = &game.constants;
+
+    let mut commanded: HashSet<ShipId> = HashSet::new();
+    let mut destinations: Vec<Position> = Vec::new();
+    let mut spawns = 0;
+    let mut spent = 0;
+
+    for command in commands {
+        let ship_id = match *command {
+            Command::Spawn => {
+                spawns += 1;
+                if spawns > 1 {
+                    issues.push(CommandIssue::DuplicateSpawn);
+                } else if me.halite < constants.ship_cost {
+                    issues.push(CommandIssue::CannotAffordSpawn { cost: constants.ship_cost, halite: me.halite });
+                }
+                spent += constants.ship_cost;
+                continue;
+            }
+            Command::Construct(ship_id) | Command::Move(ship_id, _) => ship_id,
+        };
+
+        if !me.ship_ids.contains(&ship_id) {
+            issues.push(CommandIssue::NotOwned(ship_id));
+            continue;
+        }
+
+        if !commanded.insert(ship_id) {
+            issues.push(CommandIssue::DuplicateCommand(ship_id));
+            continue;
+        }
+
+        let ship = &game.ships[&ship_id];
+        let cell = game.map.at_entity(ship);
+
+        match *command {
+            Command::Construct(_) if cell.structure.is_some() => {
+                issues.push(CommandIssue::ConstructOnStructure(ship_id));
+                destinations.push(ship.position);
+            }
+            Command::Construct(_) => {
+                spent += constants.dropoff_cost.saturating_sub(ship.halite + cell.halite);
+            }
+            Command::Move(_, Direction::Still) => destinations.push(ship.position),
+            Command::Move(_, direction) => {
+                let cost = cell.halite / move_cost_ratio_among(game, ship, game.ships.values());
+                if ship.halite < cost {
+                    issues.push(CommandIssue::CannotAffordMove { ship_id, cost, halite: ship.halite });
+                    destinations.push(ship.position);
+                } else {
+                    destinations.push(game.map.torus().offset(&ship.position, direction));
+                }
+            }
+            Command::Spawn => unreachable!(),
+        }
+    }
+
+    for ship_id in &me.ship_ids {
+        if !commanded.contains(ship_id) {
+            destinations.push(game.ships[ship_id].position);
+        }
+    }
+
+    if spawns > 0 && destinations.contains(&me.shipyard.position) {
+        issues.push(CommandIssue::ShipyardClaimed);
+    }
+
+    if spent > me.halite {
+        issues.push(CommandIssue::Overspent { spent, halite: me.halite });
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hlt::constants::Constants;
+    use hlt::fixtures;
+    use hlt::fixtures::position;
+    use hlt::fixtures::ship;
+    use hlt::ship::Ship;
+
+    // Our shipyard is at (2, 2) and the enemy's at (12, 12). Every cell
+    // holds 100 halite, so moving costs 10.
+    fn game(ships: Vec<Ship>) -> Game {
+        let map = fixtures::map(16, 16, |_| 100);
+        fixtures::game(Constants::default_for(16), map, &[position(2, 2), position(12, 12)], ships)
+    }
+
+    #[test]
+    fn accepts_a_legal_turn() {
+        let game = game(vec![ship(0, 0, 5, 5, 10), ship(0, 1, 2, 3, 0)]);
+        let commands = [Command::Move(ShipId(0), Direction::East), Command::Move(ShipId(1), Direction::Still), Command::Spawn];
+        assert_eq!(validate(&game, &commands), Vec::new());
+    }
+
+    #[test]
+    fn flags_a_second_command_for_a_ship() {
+        let game = game(vec![ship(0, 0, 5, 5, 50)]);
+        let commands = [Command::Move(ShipId(0), Direction::East), Command::Move(ShipId(0), Direction::North)];
+        assert_eq!(validate(&game, &commands), vec![CommandIssue::DuplicateCommand(ShipId(0))]);
+    }
+
+    #[test]
+    fn flags_ships_that_are_not_ours() {
+        let game = game(vec![ship(1, 0, 5, 5, 50)]);
+        assert_eq!(validate(&game, &[Command::Move(ShipId(0), Direction::East)]), vec![CommandIssue::NotOwned(ShipId(0))]);
+        assert_eq!(validate(&game, &[Command::Move(ShipId(7), Direction::East)]), vec![CommandIssue::NotOwned(ShipId(7))]);
+    }
+
+    #[test]
+    fn flags_moves_a_ship_cannot_afford() {
+        let game = game(vec![ship(0, 0, 5, 5, 9)]);
+        assert_eq!(
+            validate(&game, &[Command::Move(ShipId(0), Direction::East)]),
+            vec![CommandIssue::CannotAffordMove { ship_id: ShipId(0), cost: 10, halite: 9 }]
+        );
+    }
+
+    #[test]
+    fn inspired_ships_pay_the_inspired_rate() {
+        let mut game = game(vec![ship(0, 0, 5, 5, 5), ship(1, 1, 7, 5, 0), ship(1, 2, 5, 7, 0)]);
+        game.constants.inspired_move_cost_ratio = 20;
+        assert_eq!(validate(&game, &[Command::Move(ShipId(0), Direction::East)]), Vec::new());
+
+        game.constants.inspiration_enabled = false;
+        assert_eq!(validate(&game, &[Command::Move(ShipId(0), Direction::East)]).len(), 1);
+    }
+
+    #[test]
+    fn flags_spawns_onto_a_taken_shipyard() {
+        let game = game(vec![ship(0, 0, 2, 2, 50), ship(0, 1, 2, 3, 50)]);
+        assert_eq!(validate(&game, &[Command::Spawn]), vec![CommandIssue::ShipyardClaimed]);
+
+        let commands = [Command::Move(ShipId(0), Direction::East), Command::Move(ShipId(1), Direction::North), Command::Spawn];
+        assert_eq!(validate(&game, &commands), vec![CommandIssue::ShipyardClaimed]);
+
+        let commands = [Command::Move(ShipId(0), Direction::East), Command::Spawn];
+        assert_eq!(validate(&game, &commands), Vec::new());
+    }
+
+    #[test]
+    fn flags_spawns_we_cannot_afford() {
+        let mut game = game(Vec::new());
+        game.players[0].halite = 999;
+        assert_eq!(
+            validate(&game, &[Command::Spawn]),
+            vec![
+                CommandIssue::CannotAffordSpawn { cost: 1000, halite: 999 },
+                CommandIssue::Overspent { spent: 1000, halite: 999 },
+            ]
+        );
+        assert!(validate(&game, &[Command::Spawn, Command::Spawn]).contains(&CommandIssue::DuplicateSpawn));
+    }
+
+    #[test]
+    fn flags_spending_more_than_the_bank() {
+        // The dropoff takes 4000 - 500 - 100 from the bank and the spawn 1000.
+        let mut game = game(vec![ship(0, 0, 5, 5, 500)]);
+        game.players[0].halite = 4000;
+        assert_eq!(
+            validate(&game, &[Command::Construct(ShipId(0)), Command::Spawn]),
+            vec![CommandIssue::Overspent { spent: 4400, halite: 4000 }]
+        );
+        assert_eq!(validate(&game, &[Command::Construct(ShipId(0))]), Vec::new());
+    }
+
+    #[test]
+    fn flags_constructing_on_a_structure() {
+        let game = game(vec![ship(0, 0, 2, 2, 0), ship(0, 1, 12, 12, 0)]);
+        assert_eq!(validate(&game, &[Command::Construct(ShipId(0))]), vec![CommandIssue::ConstructOnStructure(ShipId(0))]);
+        assert_eq!(validate(&game, &[Command::Construct(ShipId(1))]), vec![CommandIssue::ConstructOnStructure(ShipId(1))]);
+    }
+}
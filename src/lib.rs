@@ -0,0 +1,6 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+
+pub mod engine;
+pub mod hlt;
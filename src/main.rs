@@ -1,16 +1,28 @@
-#[macro_use]
-extern crate lazy_static;
+extern crate my_bot;
 extern crate rand;
 
-use hlt::command::Command;
-use hlt::direction::Direction;
-use hlt::game::Game;
-use hlt::game_map::GameMap;
-use hlt::log::Log;
-use hlt::navi::Navi;
-use hlt::position::Position;
-use hlt::ship::Ship;
-use hlt::ShipId;
+use my_bot::hlt::command::Command;
+use my_bot::hlt::direction::Direction;
+use my_bot::hlt::distance_field::BaseDistances;
+use my_bot::hlt::distance_field::DistanceField;
+use my_bot::hlt::game::Game;
+use my_bot::hlt::game_map::GameMap;
+use my_bot::hlt::image::MapImage;
+use my_bot::hlt::image::Overlay;
+use my_bot::hlt::image::WHITE;
+use my_bot::hlt::inspiration::Inspiration;
+use my_bot::hlt::log::Log;
+use my_bot::hlt::navi::Navi;
+use my_bot::hlt::navi::PathCost;
+use my_bot::hlt::position::Position;
+use my_bot::hlt::reservation::ReservationTable;
+use my_bot::hlt::resolver::MoveResolver;
+use my_bot::hlt::ship::Ship;
+use my_bot::hlt::snapshot;
+use my_bot::hlt::threat::RiskPolicy;
+use my_bot::hlt::threat::ThreatMap;
+use my_bot::hlt::traffic::TrafficController;
+use my_bot::hlt::ShipId;
 use rand::Rng;
 use rand::SeedableRng;
 use rand::XorShiftRng;
@@ -19,7 +31,18 @@ use std::env;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
-mod hlt;
+// Halite a returning ship is willing to give up to save a turn.
+const RETURN_TURN_PENALTY: usize = 10;
+// How many turns ahead returning ships plan around each other.
+const RESERVATION_HORIZON: usize = 8;
+// How much enemy contact ships accept, by what they're doing.
+const COLLECTING_RISK: RiskPolicy = RiskPolicy::Trade;
+const RETURNING_RISK: RiskPolicy = RiskPolicy::Avoid;
+// Empty ships have nothing to lose but the ship, which a collision costs the
+// enemy too; with only one rival that's an even trade worth chasing.
+const EMPTY_RISK: RiskPolicy = RiskPolicy::Hunt;
+// How close to a base ships start following its lanes.
+const TRAFFIC_RADIUS: usize = 3;
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum ShipAction {
@@ -62,10 +85,15 @@ fn main() {
 
     let mut game = Game::new();
     let mut navi = Navi::new(game.map.width, game.map.height);
+    let mut inspiration = Inspiration::new(&game);
+    let mut bases = BaseDistances::new(&game, game.my_id);
+    let mut reservations = ReservationTable::new(game.map.torus(), RESERVATION_HORIZON);
+    let mut threats = ThreatMap::new(&game, game.my_id);
+    let mut traffic = TrafficController::new(&game, game.my_id, TRAFFIC_RADIUS);
     // At this point "game" variable is populated with initial map data.
     // This is a good place to do computationally expensive start-up pre-processing.
     // As soon as you call "ready" function below, the 2 second per turn timer will start.
-    Game::ready("hungnd1475");
+    game.ready("hungnd1475");
 
     Log::log(&format!(
         "Successfully created bot! My Player ID is {}.",
@@ -73,24 +101,44 @@ fn main() {
     ));
 
     let mut ship_actions: HashMap<ShipId, ShipAction> = HashMap::new();
-    let mut occupied_moves: HashMap<Position, (ShipId, bool)> = HashMap::new();
-    let mut ships_queue: Vec<ShipId> = Vec::new();
-    let mut waiting_ships: HashMap<ShipId, ShipId> = HashMap::new();
     let mut finishing = false;
+    let mut ships_built = 0;
     let mut command_queue: Vec<Command> = Vec::new();
+    // Set HALITE_IMAGE_DIR to get a picture of every turn with our moves.
+    let image_dir = env::var("HALITE_IMAGE_DIR").ok();
+    // Set HALITE_SNAPSHOT_DIR to save the state of every turn for replaying it later.
+    let snapshot_dir = env::var("HALITE_SNAPSHOT_DIR").ok();
+
+    while game.update_frame() {
+        if let Some(ref snapshot_dir) = snapshot_dir {
+            let path = format!("{}/bot-{}-turn-{:03}.json", snapshot_dir, game.my_id.0, game.turn_number);
+            if let Err(e) = snapshot::save(&game, &path) {
+                Log::log(&format!("Couldn't save {}: {}", path, e));
+            }
+        }
 
-    loop {
-        game.update_frame();
         navi.update_frame(&game);
+        inspiration.update_frame(&game);
+        bases.update_frame(&game);
+        threats.update_frame(&game);
 
         let me = &game.players[game.my_id.0];
+        ships_built += game.events.spawned.iter().filter(|id| me.ship_ids.contains(id)).count();
+        for ship in &game.events.destroyed {
+            if ship_actions.remove(&ship.id).is_some() {
+                Log::log(&format!("Lost ship {} with {} halite at {}", ship.id.0, ship.halite, ship.position));
+            }
+        }
+        for (ship, _) in &game.events.converted {
+            ship_actions.remove(&ship.id);
+        }
+
         let remaining_turns = game.constants.max_turns - game.turn_number;
 
         if !finishing {
             let max_distance = calculate_max_distance(
                 me.ship_ids.iter().map(|id| &game.ships[id]),
-                &me.shipyard.position,
-                &game.map,
+                &bases.friendly,
             );
             finishing = max_distance >= remaining_turns;
         }
@@ -113,115 +161,155 @@ fn main() {
                         }
                     }
                 }).or_insert(ShipAction::Collecting);
-            occupied_moves.insert(ship.position, (*ship_id, false));
         }
 
-        ships_queue.extend(&me.ship_ids);
-        while let Some(ship_id) = ships_queue.pop() {
-            let ship = &game.ships[&ship_id];
-            let action = ship_actions[&ship_id];
-            Log::log(&format!(
-                "Moving ship {} at {} for {:?}",
-                ship_id.0, ship.position, action
-            ));
-            let result = match action {
-                ShipAction::Collecting => {
-                    get_best_move(ship, &game.map, &navi, &occupied_moves, &waiting_ships)
-                        .unwrap_or(get_random_move(
-                            ship,
-                            &mut rng,
-                            &navi,
-                            &me.shipyard.position,
-                            &occupied_moves,
-                            &waiting_ships,
-                        ))
-                }
-                ShipAction::Dropping | ShipAction::Finishing => get_return_move(
-                    ship,
-                    &navi,
-                    &me.shipyard.position,
-                    &occupied_moves,
-                    &waiting_ships,
-                    finishing,
-                ),
+        // At the end every turn counts, so only the shortest routes will do.
+        let turn_penalty = if finishing { game.constants.max_halite } else { RETURN_TURN_PENALTY };
+        let return_cost = PathCost::new(&game.constants, turn_penalty);
+
+        // Returning ships plan around each other, nearest to home first. When
+        // finishing they all pile into the base anyway, so there's no point.
+        // Collectors only pick their step in the resolver below, so they hold
+        // no reservations and the resolver keeps them apart from returners.
+        reservations.clear();
+        let mut returning: Vec<&Ship> = me
+            .ship_ids
+            .iter()
+            .map(|id| &game.ships[id])
+            .filter(|ship| ship_actions[&ship.id] != ShipAction::Collecting)
+            .collect();
+        returning.sort_by_key(|ship| bases.friendly.distance(&ship.position));
+
+        let returning_ids: Vec<ShipId> = returning.iter().map(|ship| ship.id).collect();
+        let wants_spawn = game.turn_number <= 200 && me.halite >= game.constants.ship_cost;
+        traffic.update_frame(&game, &returning_ids, wants_spawn);
+
+        let mut return_moves: HashMap<ShipId, Vec<Direction>> = HashMap::new();
+        for ship in returning {
+            let base_position = bases.friendly.nearest(&ship.position).unwrap_or(me.shipyard.position);
+            let path = if finishing {
+                None
+            } else {
+                navi.find_cooperative_path(&game.map, &reservations, ship.id, &ship.position, &base_position, &return_cost)
             };
-            match result {
-                MoveResult::Waiting(_, blocking_ship) => {
-                    Log::log(&format!("Waiting for ship {} to resolve", blocking_ship.0));
-                    waiting_ships.insert(blocking_ship, ship_id);
+            let moves = match path {
+                Some(path) => {
+                    reservations.reserve_path(ship.id, &path.positions);
+                    navi.get_planned_moves(&ship.position, &base_position, path.first_step())
                 }
-                MoveResult::Resolved(direction) => {
-                    let position = navi.normalized_offset(&ship.position, direction);
-                    Log::log(&format!("Resolved at {:?} -> {}", direction, position));
+                None => navi.get_route_moves(&game.map, &ship.position, &base_position, &return_cost),
+            };
+            return_moves.insert(ship.id, moves);
+        }
 
-                    let &(occupied_ship, _) = occupied_moves.get(&ship.position).unwrap();
-                    if occupied_ship == ship_id {
-                        occupied_moves.remove(&ship.position);
-                    }
-                    occupied_moves.insert(position, (ship_id, true));
-                    if let Some(waiting_ship) = waiting_ships.remove(&ship_id) {
-                        ships_queue.push(waiting_ship);
-                        Log::log(&format!(
-                            "Push waiting ship {} back to resolve",
-                            waiting_ship.0
-                        ));
-                    }
-                    command_queue.push(ship.move_ship(direction));
+        // Our bases swallow any number of ships at the end.
+        let mut resolver = MoveResolver::new(game.map.torus());
+        if finishing {
+            for base_position in bases.friendly.sources() {
+                resolver.allow_stacking(base_position);
+            }
+        }
+
+        for ship_id in &me.ship_ids {
+            let ship = &game.ships[ship_id];
+            let action = ship_actions[ship_id];
+            let candidates = if ship.halite < inspiration.move_cost(ship, game.map.at_entity(ship).halite) {
+                vec![(Direction::Still, 0)]
+            } else {
+                match action {
+                    ShipAction::Collecting => get_collecting_moves(
+                        ship,
+                        &game.map,
+                        &navi,
+                        &inspiration,
+                        &mut rng,
+                        &me.shipyard.position,
+                    ),
+                    ShipAction::Dropping | ShipAction::Finishing => get_return_moves(ship, &return_moves[ship_id]),
+                }
+            };
+            let policy = match action {
+                ShipAction::Collecting if ship.halite == 0 && game.players.len() == 2 => EMPTY_RISK,
+                ShipAction::Collecting => COLLECTING_RISK,
+                _ => RETURNING_RISK,
+            };
+            let mut candidates = apply_risk(ship, candidates, &navi, &threats, policy, game.constants.ship_cost);
+            // At the end everyone piles into the bases, lanes or not.
+            if !finishing {
+                let returning = action == ShipAction::Dropping;
+                for candidate in &mut candidates {
+                    candidate.1 += traffic.adjust(ship, returning, candidate.0);
                 }
             }
+            resolver.add_ship(ship, &candidates);
         }
 
-        if game.turn_number <= 200
-            && me.halite >= game.constants.ship_cost
-            && !occupied_moves.contains_key(&me.shipyard.position)
-        {
+        let directions = resolver.resolve();
+        let mut shipyard_taken = false;
+        for ship_id in &me.ship_ids {
+            let ship = &game.ships[ship_id];
+            let direction = directions[ship_id];
+            let position = navi.normalized_offset(&ship.position, direction);
+            Log::log(&format!(
+                "Moving ship {} at {} for {:?}: {:?} -> {}",
+                ship_id.0, ship.position, ship_actions[ship_id], direction, position
+            ));
+
+            shipyard_taken = shipyard_taken || position == me.shipyard.position;
+            command_queue.push(ship.move_ship(direction));
+        }
+
+        // The traffic controller keeps the shipyard free for this unless a
+        // returning ship has waited long enough.
+        if traffic.holding_shipyard() && !shipyard_taken {
             command_queue.push(me.shipyard.spawn());
         }
 
-        occupied_moves.drain();
-        Game::end_turn(command_queue.drain(..));
+        if let Some(ref image_dir) = image_dir {
+            save_turn_image(&game, &command_queue, image_dir);
+        }
+
+        game.end_turn(command_queue.drain(..));
     }
+
+    on_game_over(&game, ships_built);
 }
 
-enum MoveResult {
-    Resolved(Direction),
-    Waiting(Direction, ShipId),
+fn on_game_over(game: &Game, ships_built: usize) {
+    let me = &game.players[game.my_id.0];
+    Log::log(&format!(
+        "Game over after turn {} with {} halite, {} ships alive of {} built.",
+        game.turn_number,
+        me.halite,
+        me.ship_ids.len(),
+        ships_built
+    ));
+    Log::flush();
 }
 
-impl MoveResult {
-    fn determine(
-        ship: &Ship,
-        direction: Direction,
-        blocking_ship: &Option<ShipId>,
-        waiting_ships: &HashMap<ShipId, ShipId>,
-    ) -> Self {
-        if direction == Direction::Still {
-            MoveResult::Resolved(direction)
-        } else {
-            if let Some(blocking_ship) = *blocking_ship {
-                if waiting_ships
-                    .get(&ship.id)
-                    .map(|&ws| ws != blocking_ship)
-                    .unwrap_or(true)
-                {
-                    MoveResult::Waiting(direction, blocking_ship)
-                } else {
-                    MoveResult::Resolved(direction)
-                }
-            } else {
-                MoveResult::Resolved(direction)
-            }
+fn save_turn_image(game: &Game, commands: &[Command], image_dir: &str) {
+    let mut overlay = Overlay::new(WHITE);
+    for command in commands {
+        if let Command::Move(ship_id, direction) = *command {
+            overlay.moves.push((game.ships[&ship_id].position, direction));
         }
     }
+
+    let mut image = MapImage::from_game(game, 8);
+    image.add_overlay(&overlay);
+
+    let path = format!("{}/bot-{}-turn-{:03}.png", image_dir, game.my_id.0, game.turn_number);
+    if let Err(e) = image.save(&path) {
+        Log::log(&format!("Couldn't save {}: {}", path, e));
+    }
 }
 
 fn calculate_max_distance<'a>(
     ships: impl Iterator<Item = &'a Ship>,
-    shipyard_position: &Position,
-    map: &GameMap,
+    friendly_bases: &DistanceField,
 ) -> usize {
     ships.fold(0, |max_distance, ship| {
-        let distance = map.calculate_distance(shipyard_position, &ship.position);
+        let distance = friendly_bases.distance(&ship.position).unwrap_or(0);
         if max_distance < distance {
             distance
         } else {
@@ -230,139 +318,73 @@ fn calculate_max_distance<'a>(
     })
 }
 
-fn get_random_move(
-    ship: &Ship,
-    rng: &mut XorShiftRng,
-    navi: &Navi,
-    shipyard_position: &Position,
-    occupied_moves: &HashMap<Position, (ShipId, bool)>,
-    waiting_ships: &HashMap<ShipId, ShipId>,
-) -> MoveResult {
-    let mut safe_moves = get_safe_moves(
-        &Direction::get_all_cardinals(),
-        ship,
-        navi,
-        occupied_moves,
-        waiting_ships,
-    );
-    while !safe_moves.is_empty() {
-        let index = rng.gen_range(0, safe_moves.len());
-        let (direction, blocking_ship) = safe_moves[index];
-        let position = navi.normalized_offset(&ship.position, direction);
-        if position != *shipyard_position {
-            return MoveResult::determine(ship, direction, &blocking_ship, waiting_ships);
-        } else {
-            safe_moves.remove(index);
-        }
-    }
-    MoveResult::Resolved(Direction::Still)
-}
-
-fn get_best_move(
+// What each move is worth to a collecting ship: the halite it would mine
+// there next turn, with staying put favoured. On a bare patch it wanders off
+// in a random direction instead, keeping clear of the shipyard.
+fn get_collecting_moves(
     ship: &Ship,
     map: &GameMap,
     navi: &Navi,
-    occupied_moves: &HashMap<Position, (ShipId, bool)>,
-    waiting_ships: &HashMap<ShipId, ShipId>,
-) -> Option<MoveResult> {
-    let ship_cell = map.at_entity(ship);
-    if ship.halite < ship_cell.halite * 10 / 100 {
-        return Some(MoveResult::Resolved(Direction::Still));
-    }
-
-    let safe_moves = get_safe_moves(
-        &Direction::get_all(),
-        ship,
-        navi,
-        occupied_moves,
-        waiting_ships,
-    );
-    let mut best_direction = Direction::Still;
-    let mut best_halite = 0;
-    let mut blocking_ship: Option<ShipId> = None;
-    let mut all_zeroes = true;
-    for (direction, bs) in safe_moves {
-        let position = navi.normalized_offset(&ship.position, direction);
-        let halite = {
-            let halite = map.at_position(&position).halite;
+    inspiration: &Inspiration,
+    rng: &mut XorShiftRng,
+    shipyard_position: &Position,
+) -> Vec<(Direction, i64)> {
+    let moves: Vec<(Direction, i64)> = Direction::get_all()
+        .into_iter()
+        .map(|direction| {
+            let position = navi.normalized_offset(&ship.position, direction);
+            let cell_halite = map.at_position(&position).halite;
+            let halite = inspiration.extraction(ship.owner, &position, ship.halite, cell_halite) as i64;
             if direction == Direction::Still {
-                halite * 3
+                (direction, halite * 3)
             } else {
-                halite
+                (direction, halite)
             }
-        };
-        all_zeroes = all_zeroes && halite == 0;
-        if best_halite <= halite {
-            best_halite = halite;
-            best_direction = direction;
-            blocking_ship = bs;
-        }
-    }
-    if all_zeroes {
-        None
-    } else {
-        Some(MoveResult::determine(
-            ship,
-            best_direction,
-            &blocking_ship,
-            waiting_ships,
-        ))
+        }).collect();
+
+    if moves.iter().any(|&(_, halite)| halite > 0) {
+        return moves;
     }
+
+    Direction::get_all_cardinals()
+        .into_iter()
+        .filter(|&direction| navi.normalized_offset(&ship.position, direction) != *shipyard_position)
+        .map(|direction| (direction, rng.gen_range(1, 5)))
+        .collect()
 }
 
-fn get_return_move(
-    ship: &Ship,
-    navi: &Navi,
-    shipyard_position: &Position,
-    occupied_moves: &HashMap<Position, (ShipId, bool)>,
-    waiting_ships: &HashMap<ShipId, ShipId>,
-    finishing: bool,
-) -> MoveResult {
-    let unsafe_moves = navi.get_unsafe_moves(&ship.position, shipyard_position);
-    if unsafe_moves.len() == 0 {
-        MoveResult::Resolved(Direction::Still)
-    } else if finishing
-        && navi.normalized_offset(&ship.position, unsafe_moves[0]) == *shipyard_position
-    {
-        MoveResult::Resolved(unsafe_moves[0])
-    } else {
-        let safe_moves = get_safe_moves(&unsafe_moves, ship, navi, occupied_moves, waiting_ships);
-        let mut result: Option<MoveResult> = None;
-        for (direction, blocking_ship) in safe_moves {
-            let r = MoveResult::determine(ship, direction, &blocking_ship, waiting_ships);
-            match r {
-                MoveResult::Resolved(_) => return r,
-                MoveResult::Waiting(_, _) => {
-                    if result.is_none() {
-                        result = Some(r)
-                    }
-                }
-            }
-        }
-        result.unwrap_or(MoveResult::Resolved(Direction::Still))
-    }
+// A returning ship values its planned moves by the cargo it carries, each
+// fallback worth half the one before, so loaded ships get through first.
+fn get_return_moves(ship: &Ship, unsafe_moves: &[Direction]) -> Vec<(Direction, i64)> {
+    unsafe_moves
+        .iter()
+        .enumerate()
+        .map(|(i, &direction)| (direction, (ship.halite as i64 + 1) >> i))
+        .collect()
 }
 
-fn get_safe_moves(
-    directions: &[Direction],
+// Drops the moves the policy rules out. Staying put can't be dropped, so when
+// it's ruled out it's made to cost the ship and its cargo instead.
+fn apply_risk(
     ship: &Ship,
+    mut candidates: Vec<(Direction, i64)>,
     navi: &Navi,
-    occupied_moves: &HashMap<Position, (ShipId, bool)>,
-    waiting_ships: &HashMap<ShipId, ShipId>,
-) -> Vec<(Direction, Option<ShipId>)> {
-    directions
-        .iter()
-        .filter(|&&direction| {
-            let position = navi.normalized_offset(&ship.position, direction);
-            occupied_moves
-                .get(&position)
-                .map(|&(ship_id, resolved)| !resolved && !waiting_ships.contains_key(&ship_id))
-                .unwrap_or(true)
-        }).map(|&direction| {
+    threats: &ThreatMap,
+    policy: RiskPolicy,
+    ship_cost: usize,
+) -> Vec<(Direction, i64)> {
+    if candidates.iter().all(|&(direction, _)| direction != Direction::Still) {
+        candidates.push((Direction::Still, 0));
+    }
+
+    candidates
+        .into_iter()
+        .filter_map(|(direction, value)| {
             let position = navi.normalized_offset(&ship.position, direction);
-            (
-                direction,
-                occupied_moves.get(&position).map(|&(ship_id, _)| ship_id),
-            )
+            match threats.adjust(policy, ship.halite, &position) {
+                Some(adjustment) => Some((direction, value + adjustment)),
+                None if direction == Direction::Still => Some((direction, value - (ship_cost + ship.halite) as i64)),
+                None => None,
+            }
         }).collect()
 }